@@ -1,14 +1,17 @@
+use serde::{Deserialize, Serialize};
+
 /// Configuration that contains all planet
 /// bounds that it must adhere to when randomly
 /// generated
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PlanetConfig {
     pub lower_pos_bound: f64,
     pub upper_pos_bound: f64,
     pub velocity_bound: f64,
     pub mass_mean: f64,
     pub mass_std: f64,
-    pub mass_to_size: f64,
+    /// Mass per unit volume, sets the radius of the body
+    pub density: f64,
 }
 
 impl PlanetConfig {
@@ -18,7 +21,7 @@ impl PlanetConfig {
         velocity_bound: f64,
         mass_mean: f64,
         mass_std: f64,
-        mass_to_size: f64,
+        density: f64,
     ) -> PlanetConfig {
         PlanetConfig {
             lower_pos_bound,
@@ -26,13 +29,50 @@ impl PlanetConfig {
             velocity_bound,
             mass_mean,
             mass_std,
-            mass_to_size,
+            density,
+        }
+    }
+
+    /// Whether random bodies can be drawn from the configuration,
+    /// the bounds must span a range and the spreads be valid
+    pub fn validate(&self) -> Result<(), String> {
+        let values = [
+            self.lower_pos_bound,
+            self.upper_pos_bound,
+            self.velocity_bound,
+            self.mass_mean,
+            self.mass_std,
+            self.density,
+        ];
+        if values.iter().any(|value| !value.is_finite()) {
+            return Err("Every value must be a finite number".to_string());
+        }
+        if self.lower_pos_bound >= self.upper_pos_bound {
+            return Err(format!(
+                "lower_pos_bound {} must be below upper_pos_bound {}",
+                self.lower_pos_bound, self.upper_pos_bound
+            ));
+        }
+        if self.velocity_bound <= 0.0 {
+            return Err(format!(
+                "velocity_bound {} must be positive",
+                self.velocity_bound
+            ));
+        }
+        if self.mass_std < 0.0 {
+            return Err(format!("mass_std {} must not be negative", self.mass_std));
+        }
+        if self.density <= 0.0 {
+            return Err(format!("density {} must be positive", self.density));
         }
+        Ok(())
     }
 }
 
-/// Trait for all bodies that have mass and a position
+/// Trait for all bodies that have mass, a position
+/// and a velocity
 pub trait CelestialBody {
     fn mass(&self) -> f64;
     fn pos(&self) -> [f64; 2];
+    fn vel(&self) -> [f64; 2];
 }
@@ -0,0 +1,147 @@
+use super::body_config::*;
+use super::planet::Planet;
+use crate::utils::colour::Colour;
+use rand::Rng;
+use rand_distr::{Distribution, LogNormal, Normal};
+use std::f64::consts::PI;
+
+// Samples below this are clamped, bodies need a positive mass
+const MIN_MASS: f64 = 0.01;
+
+/// Distribution the masses of generated bodies are drawn from
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MassDistribution {
+    Normal {
+        mean: f64,
+        std: f64,
+    },
+    /// The log of the mass is normally distributed, a long tail of heavy bodies
+    LogNormal {
+        median: f64,
+        sigma: f64,
+    },
+    /// dN/dm ~ m^-exponent between min and max, e.g. 3.5 for
+    /// the collisional cascade of an asteroid belt
+    PowerLaw {
+        min: f64,
+        max: f64,
+        exponent: f64,
+    },
+}
+
+impl MassDistribution {
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> f64 {
+        let mass = match *self {
+            MassDistribution::Normal { mean, std } => Normal::new(mean, std).unwrap().sample(rng),
+            MassDistribution::LogNormal { median, sigma } => {
+                LogNormal::new(median.ln(), sigma).unwrap().sample(rng)
+            }
+            MassDistribution::PowerLaw { min, max, exponent } => {
+                inverse_power_law(rng.gen(), min, max, 1.0 - exponent)
+            }
+        };
+        mass.max(MIN_MASS)
+    }
+}
+
+/// Invert the CDF of a density ~ x^(k - 1) between low and high at u
+fn inverse_power_law(u: f64, low: f64, high: f64, k: f64) -> f64 {
+    if k.abs() < 1e-9 {
+        // The density is ~ 1/x, which is uniform in log(x)
+        low * (high / low).powf(u)
+    } else {
+        (low.powf(k) + u * (high.powf(k) - low.powf(k))).powf(1.0 / k)
+    }
+}
+
+/// An annulus of bodies on near-circular orbits around a primary,
+/// used for asteroid belts, rings and accretion disks
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BeltConfig {
+    pub count: u32,
+    pub inner_radius: f64,
+    pub outer_radius: f64,
+    /// Surface density falls off as r^-density_exponent,
+    /// 0 spreads the bodies evenly over the area
+    pub density_exponent: f64,
+    pub mass: MassDistribution,
+    /// Random velocity on top of the circular one, as a
+    /// fraction of the circular speed, 0 for perfectly cold
+    pub velocity_dispersion: f64,
+    /// Orbit counter-clockwise on screen, i.e. with y pointing down
+    pub counter_clockwise: bool,
+}
+
+impl BeltConfig {
+    /// A wide belt of light bodies with a steep mass spectrum
+    pub fn asteroid_belt(count: u32) -> BeltConfig {
+        BeltConfig {
+            count,
+            inner_radius: 180.0,
+            outer_radius: 280.0,
+            density_exponent: 1.0,
+            mass: MassDistribution::PowerLaw {
+                min: 0.5,
+                max: 20.0,
+                exponent: 3.5,
+            },
+            velocity_dispersion: 0.03,
+            counter_clockwise: true,
+        }
+    }
+
+    /// A disk reaching close to the primary, densest in its centre
+    pub fn accretion_disk(count: u32) -> BeltConfig {
+        BeltConfig {
+            count,
+            inner_radius: 30.0,
+            outer_radius: 150.0,
+            density_exponent: 1.5,
+            mass: MassDistribution::LogNormal {
+                median: 1.0,
+                sigma: 0.5,
+            },
+            velocity_dispersion: 0.01,
+            counter_clockwise: true,
+        }
+    }
+
+    /// Generate the bodies around `primary` with ids from `first_id` on.
+    /// Velocities are circular for the primary's gravity (constant `g`)
+    /// alone, plus the dispersion, and move along with the primary
+    pub fn generate<R: Rng>(
+        &self,
+        planet_const: &PlanetConfig,
+        primary: &Planet,
+        g: f64,
+        first_id: u32,
+        colour: Colour,
+        rng: &mut R,
+    ) -> Vec<Planet> {
+        let dispersion = Normal::new(0.0, self.velocity_dispersion.max(0.0)).unwrap();
+        let sense = if self.counter_clockwise { -1.0 } else { 1.0 };
+        (0..self.count)
+            .map(|i| {
+                // Number of bodies in an annulus grows as r * r^-p
+                let k = 2.0 - self.density_exponent;
+                let radius = inverse_power_law(rng.gen(), self.inner_radius, self.outer_radius, k);
+                let angle = rng.gen_range(0.0..2.0 * PI);
+                let (sin, cos) = angle.sin_cos();
+                let position = [
+                    primary.pos()[0] + radius * cos,
+                    primary.pos()[1] + radius * sin,
+                ];
+
+                let speed = (g * primary.mass() / radius).sqrt();
+                let radial = speed * dispersion.sample(rng);
+                let tangential = speed * (1.0 + dispersion.sample(rng)) * sense;
+                let velocity = [
+                    primary.vel()[0] + radial * cos - tangential * sin,
+                    primary.vel()[1] + radial * sin + tangential * cos,
+                ];
+                let mass = self.mass.sample(rng);
+                Planet::from_state(planet_const, first_id + i, colour, position, velocity, mass)
+            })
+            .collect()
+    }
+}
@@ -1,2 +1,3 @@
 pub mod body_config;
+pub mod generator;
 pub mod planet;
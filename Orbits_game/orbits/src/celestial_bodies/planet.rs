@@ -1,14 +1,30 @@
 use super::body_config::*;
+use super::generator::MassDistribution;
+use crate::render::renderer::Renderer;
 use crate::utils::array_logic::{self as al, Length};
 use crate::utils::colour::Colour;
-use opengl_graphics::GlGraphics;
-use piston::input::UpdateArgs;
 use rand::Rng;
-use rand_distr::{Distribution, Normal};
+use serde::{Deserialize, Serialize};
+
+// Bodies smaller than this are still drawn this large
+const MIN_DRAW_RADIUS: f64 = 1.0;
+
+/// Bodies further than this from the centre are put back at random
+pub const RESET_DISTANCE: f64 = 700.0;
+
+/// Radius of a sphere of the given mass and density
+pub fn radius_from_mass(mass: f64, density: f64) -> f64 {
+    (3.0 * mass / (4.0 * std::f64::consts::PI * density)).cbrt()
+}
 
 impl Planet {
-    pub fn new(planet_const: &PlanetConfig, id: u32, colour: Colour) -> Planet {
-        let (pos, vel, mass) = Planet::configure_planet(planet_const);
+    pub fn new<R: Rng>(
+        planet_const: &PlanetConfig,
+        id: u32,
+        colour: Colour,
+        rng: &mut R,
+    ) -> Planet {
+        let (pos, vel, mass) = Planet::configure_planet(planet_const, rng);
 
         Planet {
             id,
@@ -17,108 +33,178 @@ impl Planet {
             velocity: vel,
             acceleration: [0.0, 0.0],
             mass,
-            size: [
-                mass * planet_const.mass_to_size,
-                mass * planet_const.mass_to_size,
-            ],
+            radius: radius_from_mass(mass, planet_const.density),
+            config: planet_const.clone(),
+        }
+    }
+
+    /// Create a planet with a known position, velocity
+    /// and mass rather than a randomly generated one
+    pub fn from_state(
+        planet_const: &PlanetConfig,
+        id: u32,
+        colour: Colour,
+        position: [f64; 2],
+        velocity: [f64; 2],
+        mass: f64,
+    ) -> Planet {
+        Planet {
+            id,
+            colour,
+            position,
+            velocity,
+            acceleration: [0.0, 0.0],
+            mass,
+            radius: radius_from_mass(mass, planet_const.density),
             config: planet_const.clone(),
         }
     }
 
-    fn configure_planet(planet_const: &PlanetConfig) -> ([f64; 2], [f64; 2], f64) {
-        let mut rng = rand::thread_rng();
+    fn configure_planet<R: Rng>(
+        planet_const: &PlanetConfig,
+        rng: &mut R,
+    ) -> ([f64; 2], [f64; 2], f64) {
         let x = rng.gen_range(planet_const.lower_pos_bound..planet_const.upper_pos_bound);
         let y = rng.gen_range(planet_const.lower_pos_bound..planet_const.upper_pos_bound);
         let vel_x = rng.gen_range(-planet_const.velocity_bound..planet_const.velocity_bound);
         let vel_y = rng.gen_range(-planet_const.velocity_bound..planet_const.velocity_bound);
         let pos: [f64; 2] = [x, y];
         let vel: [f64; 2] = [vel_x, vel_y];
-        let normal = Normal::new(planet_const.mass_mean, planet_const.mass_std).unwrap();
-        let mass = normal.sample(&mut rng);
+        let mass = MassDistribution::Normal {
+            mean: planet_const.mass_mean,
+            std: planet_const.mass_std,
+        }
+        .sample(rng);
 
         (pos, vel, mass)
     }
-    // Reset the planet by obtaining a new
-    // set of pos, vel, mass, size bearings
-    fn reset_planet(&mut self) {
-        let (pos, vel, mass) = Planet::configure_planet(&self.config);
+    // Reset the planet by obtaining a new set of
+    // pos and vel bearings, its mass and size are kept
+    fn reset_planet<R: Rng>(&mut self, rng: &mut R) {
+        let (pos, vel, _) = Planet::configure_planet(&self.config, rng);
         self.position = pos;
         self.velocity = vel;
     }
 
     /// Adds a 2-dimensional force to the body,
-    /// it is scaled by the body's mass before being
-    /// applied
+    /// it is scaled by the body's mass and accumulated
+    /// into the acceleration applied on the next update
     pub fn add_force(&mut self, force: [f64; 2]) {
         let scaled_force = al::scalar_mult(force, 1.0 / self.mass); // i.e. force/self.mass
-        self.velocity = al::add_arrays(self.velocity, scaled_force);
+        self.acceleration = al::add_arrays(self.acceleration, scaled_force);
+    }
+
+    /// Perform step-wise updates to velocity and position,
+    /// the accumulated acceleration is consumed in the process
+    pub fn update(&mut self, dt: f64) {
+        self.kick(dt);
+        self.drift(dt);
+        self.clear_acceleration();
+    }
+
+    /// Change the velocity by the accumulated acceleration over dt
+    pub fn kick(&mut self, dt: f64) {
+        let scaled_acc = al::scalar_mult(self.acceleration, dt); // scale by deltatime
+        self.velocity = al::add_arrays(self.velocity, scaled_acc);
     }
 
-    /// Perform step-wise updates to velocity and position
-    pub fn update(&mut self, args: &UpdateArgs) {
+    /// Move the body by its velocity over dt
+    pub fn drift(&mut self, dt: f64) {
         // scale by deltatime (e.g. move velocity[0] p/sec on x, velocity[1] p/sec on y
         // this makes movement movement frame-independent
-        let scaled_acc = al::scalar_mult(self.acceleration, args.dt); // scale by deltatime
-        self.velocity = al::add_arrays(self.velocity, scaled_acc);
-        let scaled_vel = al::scalar_mult(self.velocity, args.dt);
+        let scaled_vel = al::scalar_mult(self.velocity, dt);
         self.position = al::add_arrays(self.position, scaled_vel);
     }
 
-    /// Accept created graphical context and GL object,
-    /// draw this planet to that graphical context  
-    pub fn draw(&self, c: graphics::Context, g: &mut GlGraphics) {
-        let pos: [f64; 4] = [
-            self.position[0],
-            self.position[1],
-            self.size[0],
-            self.size[1],
-        ];
-        graphics::Rectangle::new(self.colour).draw(pos, &c.draw_state, c.transform, g);
+    /// Drop the accumulated acceleration, ready for new forces
+    pub fn clear_acceleration(&mut self) {
+        self.acceleration = [0.0, 0.0];
     }
 
-    /// Asserts distance from center, if out bounds,
-    /// reset planet's pos, vel, etc.
-    pub fn check_dist_from_centre(&mut self, centre: [f64; 2]) {
-        let dist = al::subtract_arrays(self.pos(), centre);
-        let dist_len = dist.get_length();
+    /// Emit the primitives that draw this planet to the given
+    /// renderer, as a circle centred on its position. The colour
+    /// is chosen by the caller, e.g. `colour()` or an encoding
+    pub fn draw<R: Renderer>(&self, renderer: &mut R, colour: Colour) {
+        renderer.circle(colour, self.position, self.radius.max(MIN_DRAW_RADIUS));
+    }
 
-        if (dist_len > 700.0) {
-            self.reset_planet();
+    /// Asserts distance from center, if out bounds,
+    /// reset planet's pos, vel, etc. Returns whether
+    /// the planet was reset
+    pub fn check_dist_from_centre<R: Rng>(&mut self, centre: [f64; 2], rng: &mut R) -> bool {
+        if self.is_out_of_bounds(centre) {
+            self.reset_planet(rng);
+            return true;
         }
+        false
     }
 
-    /// Checks for border collisions, turns
-    /// body around on collision
-    pub fn check_collision(&mut self, bounds: f64) {
-        if (self.position[0] + self.size[0] >= bounds) {
-            self.velocity[0] *= -1.0;
-            self.acceleration[0] *= -1.0;
-        }
-        if (self.position[0] <= 0.0) {
-            self.velocity[0] *= -1.0;
-            self.acceleration[0] *= -1.0;
-        }
-        if (self.position[1] + self.size[1] >= bounds) {
-            self.velocity[1] *= -1.0;
-            self.acceleration[1] *= -1.0;
-        }
-        if (self.position[1] <= 0.0) {
-            self.velocity[1] *= -1.0;
-            self.acceleration[1] *= -1.0;
-        }
+    /// Whether the body is too far from the centre to stay where it is
+    pub fn is_out_of_bounds(&self, centre: [f64; 2]) -> bool {
+        al::subtract_arrays(self.pos(), centre).get_length() > RESET_DISTANCE
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    pub fn acceleration(&self) -> [f64; 2] {
+        self.acceleration
+    }
+
+    /// Kinetic energy of the body, 0.5 * m * v^2
+    pub fn kinetic_energy(&self) -> f64 {
+        0.5 * self.mass * al::dot_product(self.velocity, self.velocity)
+    }
+
+    pub fn colour(&self) -> Colour {
+        self.colour
+    }
+
+    pub fn radius(&self) -> f64 {
+        self.radius
+    }
+
+    pub fn density(&self) -> f64 {
+        self.config.density
+    }
+
+    pub fn config(&self) -> &PlanetConfig {
+        &self.config
+    }
+
+    /// Change the mass of the body, its radius
+    /// is scaled along with it
+    pub fn set_mass(&mut self, mass: f64) {
+        self.mass = mass;
+        self.radius = radius_from_mass(mass, self.config.density);
+    }
+
+    pub fn set_position(&mut self, position: [f64; 2]) {
+        self.position = position;
+    }
+
+    pub fn set_velocity(&mut self, velocity: [f64; 2]) {
+        self.velocity = velocity;
+    }
+
+    /// Whether a point (e.g. the mouse cursor) lies on the
+    /// drawn body, with some slack for tiny bodies
+    pub fn contains_point(&self, point: [f64; 2], slack: f64) -> bool {
+        al::subtract_arrays(point, self.position).get_length() <= self.radius + slack
     }
 }
 
 /// This object represents a celestial body along
 /// with its properties like pos, vel and acceleration
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Planet {
     id: u32,
     colour: Colour,
     position: [f64; 2],
     velocity: [f64; 2],
     acceleration: [f64; 2],
-    size: [f64; 2],
+    radius: f64,
     mass: f64,
     config: PlanetConfig,
 }
@@ -130,4 +216,7 @@ impl CelestialBody for Planet {
     fn pos(&self) -> [f64; 2] {
         self.position
     }
+    fn vel(&self) -> [f64; 2] {
+        self.velocity
+    }
 }
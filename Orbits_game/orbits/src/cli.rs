@@ -0,0 +1,370 @@
+use crate::render::encoding::ColourEncoding;
+use crate::render::field_overlay::FieldOverlay;
+use crate::simulation::ephemeris::Projection;
+use crate::simulation::units::UnitStyle;
+use crate::sweep::SweepParameter;
+use crate::utils::colour::Palette;
+use std::path::PathBuf;
+use std::process;
+
+const USAGE: &str = "Usage: orbits [--headless] [--steps N] [--seed N] [--load FILE] [--save FILE]
+              [--record FILE] [--replay FILE]
+              [--frames DIR] [--gif FILE] [--resolution WxH] [--stride N]
+              [--svg FILE] [--colour-by KIND] [--palette NAME] [--field KIND]
+              [--tides] [--collisions] [--belt N] [--disk N] [--units KIND]
+              [--ephemeris FILE] [--projection KIND] [--lyapunov]
+              [--sweep FIELD=V1,V2,...] [--seeds LIST] [--sweep-csv FILE]
+              [--threads N] [--event-log FILE] [--close-approach D] [--ship]
+              [--level FILE] [--serve PORT] [--script FILE]
+
+  --headless    run the simulation without opening a window
+  --steps N     number of steps to run in headless mode (default 10000)
+  --seed N      seed for the random number generator
+  --load FILE   start from a saved snapshot
+  --save FILE   save a snapshot when the headless run ends
+  --record FILE record the headless run to a file, not with --script
+  --replay FILE replay a recorded session
+  --frames DIR  render headless frames offscreen as PNG files into DIR
+  --gif FILE    render headless frames offscreen into an animated GIF
+  --resolution WxH
+                size of the rendered frames (default 512x512)
+  --stride N    render a frame every N steps (default 10)
+  --svg FILE    export the last frame of the headless run as SVG
+  --colour-by KIND
+                colour rendered bodies by group, speed, energy or mass
+                (default group)
+  --palette NAME
+                colours of the generated groups of bodies,
+                classic, pastel or vivid (default classic)
+  --field KIND  overlay the gravitational field on rendered frames,
+                arrows, potential or both
+  --tides       break up bodies that cross the Roche limit of the heaviest body
+  --collisions  merge bodies that touch
+  --belt N      add an asteroid belt of N bodies around the heaviest body
+  --disk N      add an accretion disk of N bodies around the heaviest body
+  --units KIND  show values in si or astro (AU, solar masses, days) units
+  --ephemeris FILE
+                start from the state vectors in a CSV file with the columns
+                name, mass, x, y, z, vx, vy, vz, epoch
+  --projection KIND
+                flatten the ephemeris onto the invariable plane (default)
+                or the reference plane of the file
+  --lyapunov    estimate the maximal Lyapunov exponent of the headless run
+  --sweep FIELD=V1,V2,...
+                run a headless batch over every combination of the values
+                of the swept fields, can be given several times. FIELD is
+                a PlanetConfig field such as velocity_bound, of the small
+                planets or prefixed with primary. for the heavy body
+  --seeds LIST  seeds of each point of the sweep, e.g. 1,2,5 or 1-8
+  --sweep-csv FILE
+                file the summary of each run is written to (default sweep.csv)
+  --threads N   worker threads of the sweep (default one per core)
+  --event-log FILE
+                write close approaches, collisions, escapes, captures and
+                boundary resets of the headless run to FILE
+  --close-approach D
+                distance below which two bodies pass close (default 12)
+  --ship        fly a ship through the world, W thrusts, A and D turn and
+                Enter relaunches it
+  --level FILE  play the level in FILE (see assets/levels), Enter restarts
+                it and the results are kept in progress.json
+  --serve PORT  with --headless, run in real time and stream the world to
+                a viewer at http://127.0.0.1:PORT (local connections only)
+  --script FILE run the Rhai script in FILE to set the world up, its
+                on_step() is called after every step, its force(body)
+                adds to gravity and callbacks such as on_reset(body)
+                follow the bodies (see assets/scripts)";
+
+/// Options passed on the command line
+#[derive(Debug, Clone)]
+pub struct Options {
+    pub headless: bool,
+    pub steps: u64,
+    pub seed: Option<u64>,
+    pub load: Option<PathBuf>,
+    pub save: Option<PathBuf>,
+    pub record: Option<PathBuf>,
+    pub replay: Option<PathBuf>,
+    pub frames: Option<PathBuf>,
+    pub gif: Option<PathBuf>,
+    pub resolution: [u32; 2],
+    pub stride: u64,
+    pub svg: Option<PathBuf>,
+    pub colour_by: ColourEncoding,
+    pub palette: Palette,
+    pub field: FieldOverlay,
+    pub tides: bool,
+    pub collisions: bool,
+    pub belt: Option<u32>,
+    pub disk: Option<u32>,
+    pub units: Option<UnitStyle>,
+    pub ephemeris: Option<PathBuf>,
+    pub projection: Projection,
+    pub lyapunov: bool,
+    pub sweep: Vec<SweepParameter>,
+    pub seeds: Vec<u64>,
+    pub sweep_csv: PathBuf,
+    pub threads: usize,
+    pub event_log: Option<PathBuf>,
+    pub close_approach: Option<f64>,
+    pub ship: bool,
+    pub level: Option<PathBuf>,
+    pub serve: Option<u16>,
+    pub script: Option<PathBuf>,
+}
+
+impl Options {
+    /// Parse the arguments of this process, prints the
+    /// usage and exits when they are invalid
+    pub fn from_args() -> Options {
+        match Options::parse(std::env::args().skip(1)) {
+            Ok(options) => options,
+            Err(err) => {
+                eprintln!("{}\n\n{}", err, USAGE);
+                process::exit(2);
+            }
+        }
+    }
+
+    pub fn parse<I: Iterator<Item = String>>(mut args: I) -> Result<Options, String> {
+        let mut options = Options {
+            headless: false,
+            steps: 10_000,
+            seed: None,
+            load: None,
+            save: None,
+            record: None,
+            replay: None,
+            frames: None,
+            gif: None,
+            resolution: [512, 512],
+            stride: 10,
+            svg: None,
+            colour_by: ColourEncoding::Group,
+            palette: Palette::Classic,
+            field: FieldOverlay::new(),
+            tides: false,
+            collisions: false,
+            belt: None,
+            disk: None,
+            units: None,
+            ephemeris: None,
+            projection: Projection::Invariable,
+            lyapunov: false,
+            sweep: Vec::new(),
+            seeds: Vec::new(),
+            sweep_csv: PathBuf::from("sweep.csv"),
+            threads: 0,
+            event_log: None,
+            close_approach: None,
+            ship: false,
+            level: None,
+            serve: None,
+            script: None,
+        };
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--headless" => options.headless = true,
+                "--steps" => options.steps = parse_value(&arg, args.next())?,
+                "--seed" => options.seed = Some(parse_value(&arg, args.next())?),
+                "--load" => options.load = Some(PathBuf::from(value(&arg, args.next())?)),
+                "--save" => options.save = Some(PathBuf::from(value(&arg, args.next())?)),
+                "--record" => options.record = Some(PathBuf::from(value(&arg, args.next())?)),
+                "--replay" => options.replay = Some(PathBuf::from(value(&arg, args.next())?)),
+                "--frames" => options.frames = Some(PathBuf::from(value(&arg, args.next())?)),
+                "--gif" => options.gif = Some(PathBuf::from(value(&arg, args.next())?)),
+                "--resolution" => options.resolution = parse_resolution(&arg, args.next())?,
+                "--stride" => options.stride = parse_value(&arg, args.next())?,
+                "--svg" => options.svg = Some(PathBuf::from(value(&arg, args.next())?)),
+                "--colour-by" => options.colour_by = parse_encoding(&arg, args.next())?,
+                "--palette" => options.palette = parse_palette(&arg, args.next())?,
+                "--field" => options.field = parse_field(&arg, args.next())?,
+                "--tides" => options.tides = true,
+                "--collisions" => options.collisions = true,
+                "--belt" => options.belt = Some(parse_value(&arg, args.next())?),
+                "--disk" => options.disk = Some(parse_value(&arg, args.next())?),
+                "--units" => options.units = Some(parse_units(&arg, args.next())?),
+                "--ephemeris" => options.ephemeris = Some(PathBuf::from(value(&arg, args.next())?)),
+                "--projection" => options.projection = parse_projection(&arg, args.next())?,
+                "--lyapunov" => options.lyapunov = true,
+                "--sweep" => options.sweep.push(parse_sweep(&arg, args.next())?),
+                "--seeds" => options.seeds = parse_seeds(&arg, args.next())?,
+                "--sweep-csv" => options.sweep_csv = PathBuf::from(value(&arg, args.next())?),
+                "--threads" => options.threads = parse_value(&arg, args.next())?,
+                "--event-log" => options.event_log = Some(PathBuf::from(value(&arg, args.next())?)),
+                "--close-approach" => {
+                    options.close_approach = Some(parse_value(&arg, args.next())?)
+                }
+                "--ship" => options.ship = true,
+                "--level" => options.level = Some(PathBuf::from(value(&arg, args.next())?)),
+                "--serve" => options.serve = Some(parse_value(&arg, args.next())?),
+                "--script" => options.script = Some(PathBuf::from(value(&arg, args.next())?)),
+                "--help" | "-h" => {
+                    println!("{}", USAGE);
+                    process::exit(0);
+                }
+                _ => return Err(format!("Unknown argument '{}'", arg)),
+            }
+        }
+        // GIF frames store their size in 16 bits
+        let [width, height] = options.resolution;
+        if options.gif.is_some() && (width > u16::MAX as u32 || height > u16::MAX as u32) {
+            return Err(format!(
+                "Resolution {}x{} is too large for '--gif', at most {}x{}",
+                width,
+                height,
+                u16::MAX,
+                u16::MAX
+            ));
+        }
+        // A recording only holds the user's actions, not what a script did
+        if options.record.is_some() && options.script.is_some() {
+            return Err("'--record' can't be combined with '--script'".to_string());
+        }
+        Ok(options)
+    }
+}
+
+/// The value following a flag, which must be present
+fn value(flag: &str, next: Option<String>) -> Result<String, String> {
+    next.ok_or_else(|| format!("Missing value for '{}'", flag))
+}
+
+fn parse_value<T: std::str::FromStr>(flag: &str, next: Option<String>) -> Result<T, String> {
+    let raw = value(flag, next)?;
+    raw.parse()
+        .map_err(|_| format!("Invalid value '{}' for '{}'", raw, flag))
+}
+
+/// Parse a resolution given as e.g. 640x480
+fn parse_resolution(flag: &str, next: Option<String>) -> Result<[u32; 2], String> {
+    let raw = value(flag, next)?;
+    let invalid = || format!("Invalid resolution '{}' for '{}', expected WxH", raw, flag);
+    let (width, height) = raw.split_once('x').ok_or_else(invalid)?;
+    let width: u32 = width.parse().map_err(|_| invalid())?;
+    let height: u32 = height.parse().map_err(|_| invalid())?;
+    if width == 0 || height == 0 {
+        return Err(invalid());
+    }
+    Ok([width, height])
+}
+
+fn parse_encoding(flag: &str, next: Option<String>) -> Result<ColourEncoding, String> {
+    let raw = value(flag, next)?;
+    ColourEncoding::from_name(&raw).ok_or_else(|| format!("Invalid value '{}' for '{}'", raw, flag))
+}
+
+fn parse_palette(flag: &str, next: Option<String>) -> Result<Palette, String> {
+    let raw = value(flag, next)?;
+    Palette::from_name(&raw).ok_or_else(|| format!("Invalid value '{}' for '{}'", raw, flag))
+}
+
+fn parse_field(flag: &str, next: Option<String>) -> Result<FieldOverlay, String> {
+    let raw = value(flag, next)?;
+    let mut field = FieldOverlay::new();
+    match raw.as_str() {
+        "arrows" => field.arrows = true,
+        "potential" => field.potential = true,
+        "both" => (field.arrows, field.potential) = (true, true),
+        _ => return Err(format!("Invalid value '{}' for '{}'", raw, flag)),
+    }
+    Ok(field)
+}
+
+fn parse_units(flag: &str, next: Option<String>) -> Result<UnitStyle, String> {
+    let raw = value(flag, next)?;
+    match raw.as_str() {
+        "si" => Ok(UnitStyle::Si),
+        "astro" => Ok(UnitStyle::Astronomical),
+        _ => Err(format!("Invalid value '{}' for '{}'", raw, flag)),
+    }
+}
+
+fn parse_projection(flag: &str, next: Option<String>) -> Result<Projection, String> {
+    let raw = value(flag, next)?;
+    match raw.as_str() {
+        "invariable" => Ok(Projection::Invariable),
+        "reference" => Ok(Projection::ReferencePlane),
+        _ => Err(format!("Invalid value '{}' for '{}'", raw, flag)),
+    }
+}
+
+fn parse_sweep(flag: &str, next: Option<String>) -> Result<SweepParameter, String> {
+    let raw = value(flag, next)?;
+    SweepParameter::parse(&raw)
+        .map_err(|err| format!("Invalid value '{}' for '{}': {}", raw, flag, err))
+}
+
+/// Parse a list of seeds and inclusive ranges of them, e.g. 1,2,10-20
+fn parse_seeds(flag: &str, next: Option<String>) -> Result<Vec<u64>, String> {
+    let raw = value(flag, next)?;
+    let invalid = || format!("Invalid value '{}' for '{}'", raw, flag);
+    let mut seeds = Vec::new();
+    for part in raw.split(',') {
+        match part.split_once('-') {
+            Some((first, last)) => {
+                let first: u64 = first.trim().parse().map_err(|_| invalid())?;
+                let last: u64 = last.trim().parse().map_err(|_| invalid())?;
+                if first > last {
+                    return Err(invalid());
+                }
+                seeds.extend(first..=last);
+            }
+            None => seeds.push(part.trim().parse().map_err(|_| invalid())?),
+        }
+    }
+    Ok(seeds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seeds(raw: &str) -> Result<Vec<u64>, String> {
+        parse_seeds("--seeds", Some(raw.to_string()))
+    }
+
+    fn parse(args: &[&str]) -> Result<Options, String> {
+        Options::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn seeds_and_ranges() {
+        assert_eq!(seeds("1,2,5").unwrap(), vec![1, 2, 5]);
+        assert_eq!(seeds("1-4, 9").unwrap(), vec![1, 2, 3, 4, 9]);
+        assert_eq!(seeds("3-3").unwrap(), vec![3]);
+        for raw in ["", "4-2", "1,,2", "a", "-1", "1-"] {
+            assert!(seeds(raw).is_err(), "'{}' was parsed", raw);
+        }
+        assert!(parse_seeds("--seeds", None).is_err());
+    }
+
+    #[test]
+    fn sweep_options() {
+        let options = parse(&[
+            "--sweep",
+            "velocity_bound=10,20",
+            "--sweep",
+            "primary.density=5",
+            "--seeds",
+            "1-3",
+        ])
+        .unwrap();
+        assert_eq!(options.sweep.len(), 2);
+        assert_eq!(options.seeds, vec![1, 2, 3]);
+        assert!(parse(&["--sweep", "mass=1"]).is_err());
+    }
+
+    #[test]
+    fn gif_size_is_limited() {
+        assert!(parse(&["--resolution", "70000x100"]).is_ok());
+        assert!(parse(&["--gif", "out.gif", "--resolution", "70000x100"]).is_err());
+        assert!(parse(&["--resolution", "0x100"]).is_err());
+    }
+
+    #[test]
+    fn scripted_runs_are_not_recorded() {
+        assert!(parse(&["--record", "run.json"]).is_ok());
+        assert!(parse(&["--record", "run.json", "--script", "a.rhai"]).is_err());
+    }
+}
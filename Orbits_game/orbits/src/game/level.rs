@@ -0,0 +1,582 @@
+use super::mission::Mission;
+use super::ship::Ship;
+use crate::celestial_bodies::body_config::{CelestialBody, PlanetConfig};
+use crate::celestial_bodies::planet::Planet;
+use crate::simulation::settings::SimSettings;
+use crate::simulation::units::{Dimension, UnitStyle, UnitSystem};
+use crate::simulation::world::{SimRng, World};
+use crate::utils::colour;
+use rand::SeedableRng;
+use serde::Deserialize;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// A number in simulation units, or a quantity with a unit such as
+/// "1.5 AU" when the level declares a unit system
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum Amount {
+    Sim(f64),
+    Physical(String),
+}
+
+impl Amount {
+    fn resolve(&self, units: Option<&UnitSystem>, dimension: Dimension) -> Result<f64, String> {
+        match (self, units) {
+            (Amount::Sim(value), _) => Ok(*value),
+            (Amount::Physical(text), Some(units)) => units.parse(text, dimension),
+            (Amount::Physical(text), None) => {
+                Err(format!("'{}' has a unit but the level has no units", text))
+            }
+        }
+    }
+}
+
+/// A circular orbit around another body of the level
+#[derive(Debug, Clone, Deserialize)]
+pub struct CircularOrbit {
+    /// Index of the body orbited, which must come earlier in the list
+    pub around: usize,
+    pub radius: Amount,
+    /// Angle in degrees from the x-axis to the start of the
+    /// orbit, measured in the direction the body moves
+    #[serde(default)]
+    pub phase: f64,
+    /// Orbit clockwise on screen rather than counter-clockwise
+    #[serde(default)]
+    pub clockwise: bool,
+}
+
+/// Where a body or the ship starts, either as a state relative
+/// to the centre of the world or on an orbit around a body
+#[derive(Debug, Clone, Deserialize)]
+pub struct Placement {
+    pub position: Option<[Amount; 2]>,
+    pub velocity: Option<[Amount; 2]>,
+    pub orbit: Option<CircularOrbit>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct BodySpec {
+    pub mass: Amount,
+    /// Sets the radius, the default is that of the heavy body of the default world
+    #[serde(default = "default_density")]
+    pub density: f64,
+    /// Colour as #RRGGBB, by default one is picked from the id
+    pub colour: Option<String>,
+    #[serde(flatten)]
+    pub placement: Placement,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ShipSpec {
+    /// Delta-v budget
+    pub fuel: Amount,
+    #[serde(flatten)]
+    pub placement: Placement,
+}
+
+/// What the player has to do to win
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Objective {
+    /// Stay bound to the target on an orbit between the two radii
+    Orbit {
+        target: usize,
+        min_radius: Amount,
+        max_radius: Amount,
+        /// Time the orbit has to be held
+        #[serde(default = "default_hold")]
+        hold: Amount,
+    },
+    /// Get close to the target while moving along with it
+    Rendezvous {
+        target: usize,
+        distance: Amount,
+        speed: Amount,
+    },
+    /// Become unbound from the target, e.g. by a slingshot
+    Escape {
+        target: usize,
+        #[serde(default = "default_hold")]
+        hold: Amount,
+    },
+    /// Touch down on the target no faster than the speed
+    Land { target: usize, speed: Amount },
+}
+
+/// How a win is scored, fuel left and time taken count
+#[derive(Debug, Clone, Deserialize)]
+pub struct Scoring {
+    #[serde(default = "default_base")]
+    pub base: f64,
+    #[serde(default = "default_per_fuel")]
+    pub per_fuel: f64,
+    #[serde(default = "default_per_second")]
+    pub per_second: f64,
+}
+
+impl Default for Scoring {
+    fn default() -> Scoring {
+        Scoring {
+            base: default_base(),
+            per_fuel: default_per_fuel(),
+            per_second: default_per_second(),
+        }
+    }
+}
+
+fn default_density() -> f64 {
+    10.0
+}
+
+fn default_hold() -> Amount {
+    Amount::Sim(3.0)
+}
+
+fn default_base() -> f64 {
+    1000.0
+}
+
+fn default_per_fuel() -> f64 {
+    5.0
+}
+
+fn default_per_second() -> f64 {
+    2.0
+}
+
+/// A level of the game as written in its JSON file: the bodies and the
+/// ship it starts with, the objective and how it is won or lost
+#[derive(Debug, Clone, Deserialize)]
+pub struct Level {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// "si" or "astro" to allow quantities with units
+    pub units: Option<String>,
+    #[serde(default)]
+    pub collisions: bool,
+    pub bodies: Vec<BodySpec>,
+    pub ship: ShipSpec,
+    pub objective: Objective,
+    /// Simulated time before the level is lost
+    pub time_limit: Option<Amount>,
+    #[serde(default)]
+    pub scoring: Scoring,
+}
+
+/// The world, ship and mission a level starts with
+pub struct LevelStart {
+    pub world: World,
+    pub ship: Ship,
+    pub mission: Mission,
+}
+
+/// The objective of a level with every amount in simulation units
+/// and the targets given by the ids of their bodies
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Goal {
+    Orbit {
+        target: u32,
+        min_radius: f64,
+        max_radius: f64,
+        hold: f64,
+    },
+    Rendezvous {
+        target: u32,
+        distance: f64,
+        speed: f64,
+    },
+    Escape {
+        target: u32,
+        hold: f64,
+    },
+    Land {
+        target: u32,
+        speed: f64,
+    },
+}
+
+impl Level {
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Level> {
+        let contents = fs::read_to_string(path)?;
+        let level: Level = serde_json::from_str(&contents)?;
+        Ok(level)
+    }
+
+    /// Unit system quantities with units are read in
+    fn unit_system(&self, grav_const: f64) -> Result<Option<UnitSystem>, String> {
+        let style = match self.units.as_deref() {
+            None => return Ok(None),
+            Some("si") => UnitStyle::Si,
+            Some("astro") => UnitStyle::Astronomical,
+            Some(other) => return Err(format!("Unknown units '{}', expected si or astro", other)),
+        };
+        Ok(Some(UnitSystem::solar(grav_const, style)))
+    }
+
+    /// Set up the level in a world centred on `centre`, ids of
+    /// the bodies are their indices in the level file
+    pub fn start(
+        &self,
+        centre: [f64; 2],
+        grav_const: f64,
+        planet_const: &PlanetConfig,
+    ) -> Result<LevelStart, String> {
+        let units = self.unit_system(grav_const)?;
+        let units = units.as_ref();
+        let mut planets: Vec<Planet> = Vec::new();
+        for (i, body) in self.bodies.iter().enumerate() {
+            let mass = body.mass.resolve(units, Dimension::Mass)?;
+            if mass <= 0.0 {
+                return Err(format!("Body {}: mass must be above 0, got {}", i, mass));
+            }
+            let (position, velocity) = body
+                .placement
+                .resolve(&planets, centre, grav_const, units)
+                .map_err(|err| format!("Body {}: {}", i, err))?;
+            let colour = match &body.colour {
+                Some(hex) => colour::parse_hex(hex)?,
+                None => colour::for_id(i as u64),
+            };
+            let config = PlanetConfig {
+                density: body.density,
+                ..planet_const.clone()
+            };
+            planets.push(Planet::from_state(
+                &config, i as u32, colour, position, velocity, mass,
+            ));
+        }
+
+        let (position, velocity) = self
+            .ship
+            .placement
+            .resolve(&planets, centre, grav_const, units)
+            .map_err(|err| format!("Ship: {}", err))?;
+        let mut ship = Ship::new(
+            position,
+            velocity,
+            self.ship.fuel.resolve(units, Dimension::Velocity)?,
+        );
+        let goal = self.goal(planets.len(), units)?;
+        if let Goal::Land { speed, .. } = goal {
+            ship.landing_speed = speed;
+        }
+
+        let mut settings = SimSettings::new(grav_const);
+        settings.units = units.copied();
+        settings.collisions = self.collisions;
+        // Nothing is random in a level, the seed only matters for bodies that are reset
+        let world = World::new(planets, centre, settings, SimRng::seed_from_u64(0));
+        let time_limit = match &self.time_limit {
+            Some(limit) => Some(limit.resolve(units, Dimension::Time)?),
+            None => None,
+        };
+        let mission = Mission::new(self.name.clone(), goal, time_limit, self.scoring.clone());
+        Ok(LevelStart {
+            world,
+            ship,
+            mission,
+        })
+    }
+
+    fn goal(&self, body_count: usize, units: Option<&UnitSystem>) -> Result<Goal, String> {
+        let target = |index: usize| {
+            if index < body_count {
+                Ok(index as u32)
+            } else {
+                Err(format!(
+                    "The objective's target {} is not a body of the level",
+                    index
+                ))
+            }
+        };
+        let goal = match &self.objective {
+            Objective::Orbit {
+                target: index,
+                min_radius,
+                max_radius,
+                hold,
+            } => Goal::Orbit {
+                target: target(*index)?,
+                min_radius: min_radius.resolve(units, Dimension::Length)?,
+                max_radius: max_radius.resolve(units, Dimension::Length)?,
+                hold: hold.resolve(units, Dimension::Time)?,
+            },
+            Objective::Rendezvous {
+                target: index,
+                distance,
+                speed,
+            } => Goal::Rendezvous {
+                target: target(*index)?,
+                distance: distance.resolve(units, Dimension::Length)?,
+                speed: speed.resolve(units, Dimension::Velocity)?,
+            },
+            Objective::Escape {
+                target: index,
+                hold,
+            } => Goal::Escape {
+                target: target(*index)?,
+                hold: hold.resolve(units, Dimension::Time)?,
+            },
+            Objective::Land {
+                target: index,
+                speed,
+            } => Goal::Land {
+                target: target(*index)?,
+                speed: speed.resolve(units, Dimension::Velocity)?,
+            },
+        };
+        Ok(goal)
+    }
+}
+
+impl Placement {
+    /// Position and velocity in the world, `planets` being the bodies placed so far
+    fn resolve(
+        &self,
+        planets: &[Planet],
+        centre: [f64; 2],
+        grav_const: f64,
+        units: Option<&UnitSystem>,
+    ) -> Result<([f64; 2], [f64; 2]), String> {
+        let vector = |amounts: &[Amount; 2], dimension: Dimension| -> Result<[f64; 2], String> {
+            Ok([
+                amounts[0].resolve(units, dimension)?,
+                amounts[1].resolve(units, dimension)?,
+            ])
+        };
+        match (&self.position, &self.velocity, &self.orbit) {
+            (Some(position), velocity, None) => {
+                let position = vector(position, Dimension::Length)?;
+                let velocity = match velocity {
+                    Some(velocity) => vector(velocity, Dimension::Velocity)?,
+                    None => [0.0, 0.0],
+                };
+                Ok(([centre[0] + position[0], centre[1] + position[1]], velocity))
+            }
+            (None, None, Some(orbit)) => {
+                let primary = planets.get(orbit.around).ok_or_else(|| {
+                    format!(
+                        "Can only orbit one of the {} bodies before it",
+                        planets.len()
+                    )
+                })?;
+                let radius = orbit.radius.resolve(units, Dimension::Length)?;
+                if radius <= 0.0 {
+                    return Err(format!("Orbit radius must be above 0, got {}", radius));
+                }
+                let speed = (grav_const * primary.mass() / radius).sqrt();
+                let (sin, cos) = orbit.phase.to_radians().sin_cos();
+                // Screen y points down, so counter-clockwise turns towards -y
+                let sense = if orbit.clockwise { 1.0 } else { -1.0 };
+                let position = [
+                    primary.pos()[0] + radius * cos,
+                    primary.pos()[1] + sense * radius * sin,
+                ];
+                let velocity = [
+                    primary.vel()[0] - speed * sin,
+                    primary.vel()[1] + sense * speed * cos,
+                ];
+                Ok((position, velocity))
+            }
+            _ => Err("Expected either a position (and velocity) or an orbit".to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::units::{ASTRONOMICAL_UNIT, YEAR};
+
+    const CENTRE: [f64; 2] = [500.0, 500.0];
+    const GRAV_CONST: f64 = 120.0;
+
+    fn start(json: &str) -> Result<LevelStart, String> {
+        let level: Level = serde_json::from_str(json).map_err(|err| err.to_string())?;
+        let planet_const = PlanetConfig::new(0.0, 1000.0, 50.0, 10.0, 5.0, 1.0);
+        level.start(CENTRE, GRAV_CONST, &planet_const)
+    }
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() <= 1e-9 * b.abs().max(1.0)
+    }
+
+    #[test]
+    fn bodies_and_ship_are_placed() {
+        let start = start(
+            r##"{
+                "name": "Test",
+                "bodies": [
+                    { "mass": 1200, "position": [10, -20], "velocity": [1, 2] },
+                    { "mass": 3, "colour": "#ff0000", "density": 2,
+                      "orbit": { "around": 0, "radius": 100, "phase": 90 } }
+                ],
+                "ship": { "fuel": 40, "orbit": { "around": 0, "radius": 50, "clockwise": true } },
+                "objective": { "kind": "orbit", "target": 1, "min_radius": 5, "max_radius": 10 }
+            }"##,
+        )
+        .unwrap();
+        let planets = &start.world.planets;
+        assert_eq!((planets[0].id(), planets[1].id()), (0, 1));
+        assert_eq!(planets[0].pos(), [510.0, 480.0]);
+        assert_eq!(planets[1].colour(), [1.0, 0.0, 0.0, 1.0]);
+        assert_eq!(planets[1].density(), 2.0);
+        // A quarter turn counter-clockwise on screen is up, moving to the left
+        let speed = (GRAV_CONST * 1200.0 / 100.0).sqrt();
+        assert!(close(planets[1].pos()[0], 510.0) && close(planets[1].pos()[1], 380.0));
+        assert!(close(planets[1].vel()[0], 1.0 - speed) && close(planets[1].vel()[1], 2.0));
+        assert!(close(start.ship.position[0], 560.0) && close(start.ship.position[1], 480.0));
+        assert!(close(
+            start.ship.velocity[1],
+            2.0 + (GRAV_CONST * 1200.0 / 50.0).sqrt()
+        ));
+        assert_eq!(start.ship.fuel, 40.0);
+        assert_eq!(
+            start.mission.goal,
+            Goal::Orbit {
+                target: 1,
+                min_radius: 5.0,
+                max_radius: 10.0,
+                hold: 3.0
+            }
+        );
+        assert_eq!(start.mission.time_limit, None);
+        assert!(start.world.settings.units.is_none());
+    }
+
+    #[test]
+    fn quantities_are_read_in_the_units_of_the_level() {
+        let start = start(
+            r#"{
+                "name": "Astro",
+                "units": "astro",
+                "bodies": [{ "mass": "1 Msun", "position": ["0 AU", "0 AU"] }],
+                "ship": { "fuel": "10 km/s", "orbit": { "around": 0, "radius": "1 AU" } },
+                "objective": { "kind": "escape", "target": 0, "hold": "30 d" },
+                "time_limit": "2 yr"
+            }"#,
+        )
+        .unwrap();
+        let units = start.world.settings.units.unwrap();
+        assert!(close(start.world.planets[0].mass(), 7000.0));
+        let radius = start.ship.position[0] - CENTRE[0];
+        assert!(close(
+            units.to_si(radius, Dimension::Length),
+            ASTRONOMICAL_UNIT
+        ));
+        assert!(close(
+            units.to_si(start.ship.fuel, Dimension::Velocity),
+            10_000.0
+        ));
+        let limit = start.mission.time_limit.unwrap();
+        assert!(close(units.to_si(limit, Dimension::Time), 2.0 * YEAR));
+        let lines = start.mission.lines(Some(&units));
+        assert_eq!(lines[2], "status       2.000 yr left");
+        match start.mission.goal {
+            Goal::Escape { target, hold } => {
+                assert_eq!(target, 0);
+                assert!(close(units.to_si(hold, Dimension::Time), 30.0 * 86_400.0));
+            }
+            goal => panic!("Unexpected goal {:?}", goal),
+        }
+    }
+
+    #[test]
+    fn mistakes_in_a_level_are_reported() {
+        let level = |bodies: &str, objective: &str, extra: &str| {
+            format!(
+                r#"{{ "name": "Bad", "bodies": [{}], "ship": {{ "fuel": 1, "position": [0, 0] }},
+                     "objective": {} {} }}"#,
+                bodies, objective, extra
+            )
+        };
+        let heavy = r#"{ "mass": 10, "position": [0, 0] }"#;
+        let land = r#"{ "kind": "land", "target": 0, "speed": 1 }"#;
+        let errors = [
+            (
+                level(heavy, r#"{ "kind": "land", "target": 3, "speed": 1 }"#, ""),
+                "target 3",
+            ),
+            (
+                level(r#"{ "mass": "1 Msun", "position": [0, 0] }"#, land, ""),
+                "no units",
+            ),
+            (
+                level(heavy, land, r#", "units": "imperial""#),
+                "Unknown units",
+            ),
+            (
+                level(heavy, land, r#", "units": "si", "time_limit": "3 AU""#),
+                "Expected a time",
+            ),
+            (
+                level(
+                    r#"{ "mass": 1, "orbit": { "around": 0, "radius": 5 } }"#,
+                    land,
+                    "",
+                ),
+                "Body 0",
+            ),
+            (
+                level(
+                    r#"{ "mass": 1, "position": [0, 0], "orbit": { "around": 0, "radius": 5 } }"#,
+                    land,
+                    "",
+                ),
+                "Expected either",
+            ),
+            (
+                level(heavy, r#"{ "kind": "hover", "target": 0 }"#, ""),
+                "unknown variant",
+            ),
+            (
+                level(r#"{ "mass": 0, "position": [0, 0] }"#, land, ""),
+                "Body 0: mass must be above 0",
+            ),
+            (
+                level(
+                    r#"{ "mass": "-2 kg", "position": [0, 0] }"#,
+                    land,
+                    r#", "units": "si""#,
+                ),
+                "Body 0: mass must be above 0",
+            ),
+            (
+                level(
+                    &format!(
+                        r#"{}, {{ "mass": 1, "orbit": {{ "around": 0, "radius": 0 }} }}"#,
+                        heavy
+                    ),
+                    land,
+                    "",
+                ),
+                "Body 1: Orbit radius must be above 0",
+            ),
+        ];
+        for (json, expected) in errors {
+            match start(&json) {
+                Ok(_) => panic!("{} was started", json),
+                Err(err) => assert!(err.contains(expected), "'{}' for {}", err, json),
+            }
+        }
+    }
+
+    #[test]
+    fn sample_levels_start() {
+        let levels = [
+            include_str!("../../assets/levels/first_orbit.json"),
+            include_str!("../../assets/levels/rendezvous.json"),
+            include_str!("../../assets/levels/slingshot.json"),
+            include_str!("../../assets/levels/landing.json"),
+        ];
+        for json in levels {
+            let start = start(json).unwrap();
+            assert!(!start.world.planets.is_empty());
+            assert!(start.ship.fuel > 0.0);
+        }
+    }
+}
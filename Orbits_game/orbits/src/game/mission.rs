@@ -0,0 +1,194 @@
+use super::level::{Goal, Scoring};
+use super::ship::{Ship, ShipState};
+use crate::celestial_bodies::body_config::CelestialBody;
+use crate::simulation::orbit::OrbitalElements;
+use crate::simulation::units::{self, Dimension, UnitSystem};
+use crate::simulation::world::{World, FIXED_DT};
+use crate::utils::array_logic::{subtract_arrays, Length};
+
+/// How a mission ended
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome {
+    Won { score: u32 },
+    Lost { reason: String },
+}
+
+/// Follows the player through a level, checking the
+/// objective and the ways to lose after every step
+pub struct Mission {
+    pub name: String,
+    pub goal: Goal,
+    pub time_limit: Option<f64>,
+    pub scoring: Scoring,
+    pub elapsed: f64,
+    /// Time the objective has been met without a break
+    held: f64,
+    pub outcome: Option<Outcome>,
+}
+
+impl Mission {
+    pub fn new(name: String, goal: Goal, time_limit: Option<f64>, scoring: Scoring) -> Mission {
+        Mission {
+            name,
+            goal,
+            time_limit,
+            scoring,
+            elapsed: 0.0,
+            held: 0.0,
+            outcome: None,
+        }
+    }
+
+    /// Check the mission after the world and the ship took a step,
+    /// returns the outcome once when the mission ends
+    pub fn update(&mut self, world: &World, ship: &Ship) -> Option<Outcome> {
+        if self.outcome.is_some() {
+            return None;
+        }
+        let dt = FIXED_DT * world.settings.time_scale;
+        self.elapsed += dt;
+
+        let outcome = match self.check(world, ship, dt) {
+            Some(Ok(())) => Outcome::Won {
+                score: self.score(ship),
+            },
+            Some(Err(reason)) => Outcome::Lost { reason },
+            None => return None,
+        };
+        self.outcome = Some(outcome.clone());
+        Some(outcome)
+    }
+
+    /// Some(Ok) when the objective is met, Some(Err) with the
+    /// reason when the mission failed and None while it goes on
+    fn check(&mut self, world: &World, ship: &Ship, dt: f64) -> Option<Result<(), String>> {
+        let landed_on_target = matches!(
+            (self.goal, ship.state),
+            (Goal::Land { target, .. }, ShipState::Landed { on }) if on == target
+        );
+        match ship.state {
+            _ if landed_on_target => return Some(Ok(())),
+            ShipState::Flying => {}
+            ShipState::Landed { on } => return Some(Err(format!("Landed on #{} instead", on))),
+            ShipState::Crashed { into } => return Some(Err(format!("Crashed into #{}", into))),
+            ShipState::Lost => return Some(Err("Lost in space".to_string())),
+        }
+        if self.time_limit.is_some_and(|limit| self.elapsed > limit) {
+            return Some(Err("Out of time".to_string()));
+        }
+
+        let target = self.target();
+        let target = match world.planets.iter().find(|planet| planet.id() == target) {
+            Some(target) => target,
+            None => return Some(Err(format!("Target #{} was destroyed", target))),
+        };
+        let rel_pos = subtract_arrays(ship.position, target.pos());
+        let rel_vel = subtract_arrays(ship.velocity, target.vel());
+        let elements = OrbitalElements::from_state(
+            rel_pos,
+            rel_vel,
+            world.settings.grav_const * target.mass(),
+        );
+        let met = match self.goal {
+            Goal::Orbit {
+                min_radius,
+                max_radius,
+                hold,
+                ..
+            } => {
+                let within = elements.periapsis() >= min_radius
+                    && elements
+                        .apoapsis()
+                        .is_some_and(|apoapsis| apoapsis <= max_radius);
+                self.hold(within, hold, dt)
+            }
+            Goal::Rendezvous {
+                distance, speed, ..
+            } => rel_pos.get_length() <= distance && rel_vel.get_length() <= speed,
+            Goal::Escape { hold, .. } => self.hold(!elements.is_bound(), hold, dt),
+            // Won by landing, which is handled above
+            Goal::Land { .. } => false,
+        };
+        if met {
+            Some(Ok(()))
+        } else {
+            None
+        }
+    }
+
+    /// Whether a condition has held for long enough
+    fn hold(&mut self, condition: bool, hold: f64, dt: f64) -> bool {
+        self.held = if condition { self.held + dt } else { 0.0 };
+        condition && self.held >= hold
+    }
+
+    fn target(&self) -> u32 {
+        match self.goal {
+            Goal::Orbit { target, .. }
+            | Goal::Rendezvous { target, .. }
+            | Goal::Escape { target, .. }
+            | Goal::Land { target, .. } => target,
+        }
+    }
+
+    fn score(&self, ship: &Ship) -> u32 {
+        let score = self.scoring.base + self.scoring.per_fuel * ship.fuel
+            - self.scoring.per_second * self.elapsed;
+        score.max(0.0).round() as u32
+    }
+
+    /// What the objective is, in a few words
+    pub fn objective(&self, units: Option<&UnitSystem>) -> String {
+        let length = |value| units::show(units, value, Dimension::Length);
+        let speed = |value| units::show(units, value, Dimension::Velocity);
+        let time = |value| units::show(units, value, Dimension::Time);
+        match self.goal {
+            Goal::Orbit {
+                target,
+                min_radius,
+                max_radius,
+                hold,
+            } => format!(
+                "orbit #{} within {} - {} for {}",
+                target,
+                length(min_radius),
+                length(max_radius),
+                time(hold)
+            ),
+            Goal::Rendezvous {
+                target,
+                distance,
+                speed: max,
+            } => {
+                format!(
+                    "meet #{} within {} below {}",
+                    target,
+                    length(distance),
+                    speed(max)
+                )
+            }
+            Goal::Escape { target, .. } => format!("escape from #{}", target),
+            Goal::Land { target, speed: max } => {
+                format!("land on #{} below {}", target, speed(max))
+            }
+        }
+    }
+
+    /// Text lines describing the mission
+    pub fn lines(&self, units: Option<&UnitSystem>) -> Vec<String> {
+        let time = |value| units::show(units, value, Dimension::Time);
+        let status = match &self.outcome {
+            None => match self.time_limit {
+                Some(limit) => format!("{} left", time((limit - self.elapsed).max(0.0))),
+                None => time(self.elapsed),
+            },
+            Some(Outcome::Won { score }) => format!("won, score {}", score),
+            Some(Outcome::Lost { reason }) => format!("lost: {}", reason),
+        };
+        vec![
+            format!("mission      {}", self.name),
+            format!("goal         {}", self.objective(units)),
+            format!("status       {}", status),
+        ]
+    }
+}
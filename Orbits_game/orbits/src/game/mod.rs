@@ -0,0 +1,4 @@
+pub mod level;
+pub mod mission;
+pub mod progress;
+pub mod ship;
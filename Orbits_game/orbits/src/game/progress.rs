@@ -0,0 +1,49 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// How the player did on one level
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LevelProgress {
+    pub attempts: u32,
+    pub wins: u32,
+    pub best_score: Option<u32>,
+}
+
+/// Results of every level played, kept in a local file
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Progress {
+    pub levels: BTreeMap<String, LevelProgress>,
+}
+
+impl Progress {
+    /// Read the progress file, a missing file means nothing was played yet
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Progress> {
+        match fs::read_to_string(path) {
+            Ok(contents) => Ok(serde_json::from_str(&contents)?),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Progress::default()),
+            Err(err) => Err(err),
+        }
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let contents = serde_json::to_string_pretty(self)?;
+        fs::write(path, contents)
+    }
+
+    /// Count an attempt at the level, with the score when it was won
+    pub fn record(&mut self, level: &str, score: Option<u32>) {
+        let progress = self.levels.entry(level.to_string()).or_default();
+        progress.attempts += 1;
+        if let Some(score) = score {
+            progress.wins += 1;
+            progress.best_score = progress.best_score.max(Some(score));
+        }
+    }
+
+    pub fn get(&self, level: &str) -> Option<&LevelProgress> {
+        self.levels.get(level)
+    }
+}
@@ -0,0 +1,365 @@
+use crate::celestial_bodies::body_config::CelestialBody;
+use crate::render::renderer::Renderer;
+use crate::simulation::units::{self, Dimension, UnitSystem};
+use crate::simulation::world::{World, FIXED_DT};
+use crate::utils::array_logic::{add_arrays, scalar_mult, subtract_arrays, Length};
+use crate::utils::colour::{self, Colour};
+use piston::input::Key;
+use std::f64::consts::PI;
+
+const HULL: Colour = [0.4, 0.9, 1.0, 1.0];
+const FLAME: Colour = [1.0, 0.6, 0.2, 1.0];
+const PREDICTION: Colour = [0.4, 0.9, 1.0, 0.6];
+// Acceleration of the engine, in simulation units
+const THRUST: f64 = 60.0;
+// Turning rate in radians per second of simulated time
+const TURN_RATE: f64 = 3.0;
+// Size of the drawn hull, also the radius used for crashes
+const SHIP_RADIUS: f64 = 4.0;
+// Steps looked ahead for the predicted trajectory, and
+// the number of steps between two predictions
+const PREDICTION_STEPS: usize = 600;
+const PREDICTION_INTERVAL: u64 = 15;
+// Fastest touch down, relative to the body, that does not crash the ship
+const LANDING_SPEED: f64 = 10.0;
+// Ships this far from the centre are lost, as
+// bodies are put back at random at that distance
+const LOST_DISTANCE: f64 = 700.0;
+
+/// Where the ship is at
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ShipState {
+    Flying,
+    /// Touched down gently on the body with this id and rides along with it
+    Landed {
+        on: u32,
+    },
+    /// Hit the body with this id
+    Crashed {
+        into: u32,
+    },
+    /// Drifted out of the world
+    Lost,
+}
+
+/// What the player is asking the ship to do
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Controls {
+    pub thrust: bool,
+    /// -1 turns counter-clockwise on screen, 1 clockwise
+    pub turn: f64,
+}
+
+impl Controls {
+    /// Follow the W (thrust), A and D (turn) keys being pressed
+    /// or released, returns whether the key controls the ship
+    pub fn handle_key(&mut self, key: Key, pressed: bool) -> bool {
+        match key {
+            Key::W => self.thrust = pressed,
+            Key::A => self.turn = if pressed { -1.0 } else { self.turn.max(0.0) },
+            Key::D => self.turn = if pressed { 1.0 } else { self.turn.min(0.0) },
+            _ => return false,
+        }
+        true
+    }
+}
+
+/// A player-controlled ship. It feels the gravity of every body but is
+/// too light to pull on them, so it moves like a probe through the world
+#[derive(Debug, Clone)]
+pub struct Ship {
+    pub position: [f64; 2],
+    pub velocity: [f64; 2],
+    /// Direction the nose points in, radians from the x-axis
+    pub heading: f64,
+    /// Delta-v the engine has left
+    pub fuel: f64,
+    pub state: ShipState,
+    pub controls: Controls,
+    /// Fastest touch down that counts as a landing
+    pub landing_speed: f64,
+    /// Where on the body the ship landed, relative to its centre
+    landed_at: [f64; 2],
+    prediction: Vec<[f64; 2]>,
+    predicted_at: Option<u64>,
+}
+
+impl Ship {
+    /// A ship on a circular orbit of the given radius around the heaviest
+    /// body, moving counter-clockwise on screen like the generated belts
+    pub fn launch(world: &World, radius: f64, fuel: f64) -> Option<Ship> {
+        let primary = &world.planets[world.heaviest()?];
+        let speed = (world.settings.grav_const * primary.mass() / radius).sqrt();
+        let position = add_arrays(primary.pos(), [radius, 0.0]);
+        Some(Ship::new(
+            position,
+            add_arrays(primary.vel(), [0.0, -speed]),
+            fuel,
+        ))
+    }
+
+    /// A ship with the nose pointing where it moves
+    pub fn new(position: [f64; 2], velocity: [f64; 2], fuel: f64) -> Ship {
+        Ship {
+            position,
+            velocity,
+            heading: velocity[1].atan2(velocity[0]),
+            fuel,
+            state: ShipState::Flying,
+            controls: Controls::default(),
+            landing_speed: LANDING_SPEED,
+            landed_at: [0.0, 0.0],
+            prediction: Vec::new(),
+            predicted_at: None,
+        }
+    }
+
+    pub fn is_flying(&self) -> bool {
+        self.state == ShipState::Flying
+    }
+
+    /// Move the ship along with a world that has just taken a step, returns
+    /// the new state when the ship landed, crashed or got lost
+    pub fn step(&mut self, world: &World) -> Option<ShipState> {
+        if let ShipState::Landed { on } = self.state {
+            if let Some(planet) = world.planets.iter().find(|planet| planet.id() == on) {
+                self.position = add_arrays(planet.pos(), self.landed_at);
+                self.velocity = planet.vel();
+            }
+            return None;
+        }
+        if !self.is_flying() {
+            return None;
+        }
+        let dt = FIXED_DT * world.settings.time_scale;
+        self.heading += self.controls.turn * TURN_RATE * dt;
+
+        let mut acceleration = world.acceleration_at(self.position);
+        if self.controls.thrust && self.fuel > 0.0 {
+            let burn = (THRUST * dt).min(self.fuel);
+            self.fuel -= burn;
+            let nose = [self.heading.cos(), self.heading.sin()];
+            acceleration = add_arrays(acceleration, scalar_mult(nose, burn / dt));
+        }
+        self.velocity = add_arrays(self.velocity, scalar_mult(acceleration, dt));
+        self.position = add_arrays(self.position, scalar_mult(self.velocity, dt));
+
+        let hit = world.planets.iter().find(|planet| {
+            subtract_arrays(planet.pos(), self.position).get_length()
+                < planet.radius() + SHIP_RADIUS
+        });
+        self.state = match hit {
+            Some(planet)
+                if subtract_arrays(self.velocity, planet.vel()).get_length()
+                    <= self.landing_speed =>
+            {
+                self.landed_at = subtract_arrays(self.position, planet.pos());
+                self.velocity = planet.vel();
+                ShipState::Landed { on: planet.id() }
+            }
+            Some(planet) => ShipState::Crashed { into: planet.id() },
+            None if subtract_arrays(self.position, world.centre).get_length() > LOST_DISTANCE => {
+                ShipState::Lost
+            }
+            None => ShipState::Flying,
+        };
+        if self.is_flying() {
+            None
+        } else {
+            Some(self.state)
+        }
+    }
+
+    /// Path the ship would follow when coasting from now on, updated every
+    /// few steps by running a copy of the world ahead. Stops where it
+    /// would crash or get lost
+    pub fn predict(&mut self, world: &World) {
+        if self
+            .predicted_at
+            .is_some_and(|at| world.steps < at + PREDICTION_INTERVAL)
+        {
+            return;
+        }
+        self.predicted_at = Some(world.steps);
+        self.prediction.clear();
+        if !self.is_flying() {
+            return;
+        }
+
+        let mut future = world.fork();
+        future.detector.enabled = false;
+        let mut ghost = self.clone();
+        ghost.controls = Controls::default();
+        for _ in 0..PREDICTION_STEPS {
+            future.advance();
+            let ended = ghost.step(&future);
+            self.prediction.push(ghost.position);
+            if ended.is_some() {
+                break;
+            }
+        }
+    }
+
+    /// Predict again on the next step, e.g. after the world was changed
+    pub fn invalidate_prediction(&mut self) {
+        self.predicted_at = None;
+    }
+
+    pub fn draw<R: Renderer>(&self, renderer: &mut R) {
+        // The predicted path fades out towards its end
+        let count = self.prediction.len().max(1) as f64;
+        for (i, pair) in self.prediction.windows(2).enumerate() {
+            let colour = colour::fade(PREDICTION, 1.0 - i as f64 / count);
+            renderer.line(
+                colour,
+                0.5,
+                [pair[0][0], pair[0][1], pair[1][0], pair[1][1]],
+            );
+        }
+
+        let point = |angle: f64, length: f64| {
+            let angle = self.heading + angle;
+            [
+                self.position[0] + length * angle.cos(),
+                self.position[1] + length * angle.sin(),
+            ]
+        };
+        let nose = point(0.0, 2.0 * SHIP_RADIUS);
+        let left = point(2.5, SHIP_RADIUS);
+        let right = point(-2.5, SHIP_RADIUS);
+        let hull = match self.state {
+            ShipState::Flying | ShipState::Landed { .. } => HULL,
+            _ => colour::RED,
+        };
+        for (from, to) in [(nose, left), (left, right), (right, nose)] {
+            renderer.line(hull, 1.0, [from[0], from[1], to[0], to[1]]);
+        }
+        if self.is_flying() && self.controls.thrust && self.fuel > 0.0 {
+            let tail = point(PI, 1.5 * SHIP_RADIUS);
+            let flame = point(PI, 3.0 * SHIP_RADIUS);
+            renderer.line(FLAME, 1.0, [tail[0], tail[1], flame[0], flame[1]]);
+        }
+    }
+
+    /// Text lines describing the ship
+    pub fn lines(&self, units: Option<&UnitSystem>) -> Vec<String> {
+        let state = match self.state {
+            ShipState::Flying if self.fuel <= 0.0 => "out of fuel".to_string(),
+            ShipState::Flying => "flying".to_string(),
+            ShipState::Landed { on } => format!("landed on #{}", on),
+            ShipState::Crashed { into } => format!("crashed into #{}", into),
+            ShipState::Lost => "lost in space".to_string(),
+        };
+        vec![
+            format!("ship         {}", state),
+            format!(
+                "delta-v      {}",
+                units::show(units, self.fuel, Dimension::Velocity)
+            ),
+            format!(
+                "speed        {}",
+                units::show(units, self.velocity.get_length(), Dimension::Velocity)
+            ),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::celestial_bodies::body_config::PlanetConfig;
+    use crate::celestial_bodies::planet::Planet;
+    use crate::simulation::settings::SimSettings;
+    use crate::simulation::world::SimRng;
+    use rand::SeedableRng;
+
+    const CENTRE: [f64; 2] = [400.0, 400.0];
+
+    /// A world with a weak gravity, around a single body at the centre when asked
+    fn world(with_body: bool) -> World {
+        let config = PlanetConfig::new(0.0, 800.0, 0.0, 1.0, 0.0, 1.0);
+        let planets = if with_body {
+            vec![Planet::from_state(
+                &config,
+                7,
+                colour::WHITE,
+                CENTRE,
+                [0.0; 2],
+                1000.0,
+            )]
+        } else {
+            Vec::new()
+        };
+        World::new(
+            planets,
+            CENTRE,
+            SimSettings::new(1.0),
+            SimRng::seed_from_u64(0),
+        )
+    }
+
+    /// A ship just above the body, falling towards it
+    fn falling(world: &World, speed: f64) -> Ship {
+        let height = world.planets[0].radius() + SHIP_RADIUS + 0.01;
+        Ship::new([CENTRE[0] + height, CENTRE[1]], [-speed, 0.0], 10.0)
+    }
+
+    #[test]
+    fn gentle_touch_down_lands_and_fast_one_crashes() {
+        let mut world = world(true);
+        let mut gentle = falling(&world, 0.5 * LANDING_SPEED);
+        let mut fast = falling(&world, 2.0 * LANDING_SPEED);
+        world.advance();
+        assert_eq!(gentle.step(&world), Some(ShipState::Landed { on: 7 }));
+        assert_eq!(fast.step(&world), Some(ShipState::Crashed { into: 7 }));
+        // A landed ship rides along with the body
+        assert_eq!(gentle.velocity, world.planets[0].vel());
+
+        let mut strict = falling(&world, 0.5 * LANDING_SPEED);
+        strict.landing_speed = 0.1 * LANDING_SPEED;
+        world.advance();
+        assert_eq!(strict.step(&world), Some(ShipState::Crashed { into: 7 }));
+    }
+
+    #[test]
+    fn engine_burns_no_more_than_the_fuel_left() {
+        let mut world = world(false);
+        let mut ship = Ship::new(CENTRE, [1.0, 0.0], 0.1);
+        ship.controls.thrust = true;
+        world.advance();
+        assert_eq!(ship.step(&world), None);
+        assert_eq!(ship.fuel, 0.0);
+        assert!((ship.velocity[0] - 1.1).abs() < 1e-12);
+        // With the tank empty the engine does nothing
+        world.advance();
+        ship.step(&world);
+        assert!((ship.velocity[0] - 1.1).abs() < 1e-12);
+    }
+
+    #[test]
+    fn ship_leaving_the_world_is_lost() {
+        let mut world = world(false);
+        let mut ship = Ship::new(
+            [CENTRE[0] + LOST_DISTANCE - 0.5, CENTRE[1]],
+            [120.0, 0.0],
+            1.0,
+        );
+        world.advance();
+        assert_eq!(ship.step(&world), Some(ShipState::Lost));
+        assert_eq!(ship.step(&world), None);
+    }
+
+    #[test]
+    fn prediction_stops_at_a_crash() {
+        let world = world(true);
+        let mut ship = Ship::new([CENTRE[0] + 100.0, CENTRE[1]], [-40.0, 0.0], 1.0);
+        ship.predict(&world);
+        assert!(!ship.prediction.is_empty() && ship.prediction.len() < PREDICTION_STEPS);
+        let end = ship.prediction[ship.prediction.len() - 1];
+        let reach = world.planets[0].radius() + SHIP_RADIUS;
+        assert!(subtract_arrays(end, CENTRE).get_length() < reach);
+        // The ship itself did not move
+        assert_eq!(ship.position, [CENTRE[0] + 100.0, CENTRE[1]]);
+        assert!(ship.is_flying());
+    }
+}
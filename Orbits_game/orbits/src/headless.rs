@@ -0,0 +1,202 @@
+use crate::cli::Options;
+use crate::render::export::FrameExport;
+use crate::render::renderer;
+use crate::render::svg::SvgRenderer;
+use crate::server::Server;
+use crate::simulation::lyapunov::LyapunovEstimator;
+use crate::simulation::recording::{Recorder, Replay};
+use crate::simulation::script::Script;
+use crate::simulation::snapshot::Snapshot;
+use crate::simulation::units::{self, Dimension};
+use crate::simulation::world::World;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+/// Run the simulation without a window for the number of steps
+/// in the options, calling the script after every step and
+/// saving a snapshot at the end
+pub fn run(options: &Options, mut world: World, script: Option<&Script>) {
+    let recorder = options.record.as_ref().map(|_| Recorder::start(&world));
+    let mut export = frame_export(options, &world);
+    let mut lyapunov = options.lyapunov.then(|| LyapunovEstimator::start(&world));
+    let mut log = event_log(options);
+    let mut server = serve(options);
+    for _ in 0..options.steps {
+        world.advance();
+        if let Some(script) = script {
+            script.step(&mut world);
+        }
+        observe_frame(&mut export, &world);
+        log_events(&mut log, &mut world);
+        if let Some(server) = server.as_mut() {
+            server.observe(&world);
+        }
+        if let Some(estimator) = lyapunov.as_mut() {
+            estimator.observe(&world);
+        }
+    }
+    print_summary(&world);
+    if let Some(estimator) = &lyapunov {
+        for line in estimator.lines(&world) {
+            println!("{}", line);
+        }
+    }
+
+    if let (Some(path), Some(recorder)) = (&options.record, recorder) {
+        match recorder.finish(&world).save(path) {
+            Ok(()) => println!("Saved recording to {}", path.display()),
+            Err(err) => eprintln!("Could not save recording to {}: {}", path.display(), err),
+        }
+    }
+    save_snapshot(options, &world);
+    save_svg(options, &world);
+}
+
+/// Replay a recorded session without a window and
+/// check that it ends in the recorded state
+pub fn run_replay(options: &Options, mut replay: Replay, mut world: World) {
+    let mut export = frame_export(options, &world);
+    let mut log = event_log(options);
+    let mut server = serve(options);
+    loop {
+        replay.apply_due(&mut world);
+        if replay.is_finished(&world) {
+            break;
+        }
+        world.advance();
+        observe_frame(&mut export, &world);
+        log_events(&mut log, &mut world);
+        if let Some(server) = server.as_mut() {
+            server.observe(&world);
+        }
+    }
+    print_summary(&world);
+    println!("Replay matches recording: {}", replay.matches(&world));
+    save_snapshot(options, &world);
+    save_svg(options, &world);
+}
+
+/// Set up offscreen rendering when frames or a GIF are asked for,
+/// the frames show the area around the centre of the world
+fn frame_export(options: &Options, world: &World) -> Option<FrameExport> {
+    if options.frames.is_none() && options.gif.is_none() {
+        return None;
+    }
+    let [width, height] = options.resolution;
+    let view = [world.centre[0] * 2.0, world.centre[1] * 2.0];
+    let export = FrameExport::new(
+        width,
+        height,
+        view,
+        options.stride,
+        options.colour_by,
+        options.field,
+    );
+    let mut export = match export {
+        Ok(export) => export,
+        Err(err) => {
+            eprintln!("Could not render frames: {}", err);
+            return None;
+        }
+    };
+    if let Some(dir) = &options.frames {
+        if let Err(err) = export.add_png_sequence(dir.clone()) {
+            eprintln!("Could not write frames to {}: {}", dir.display(), err);
+            return None;
+        }
+    }
+    if let Some(path) = &options.gif {
+        if let Err(err) = export.add_gif(path.clone()) {
+            eprintln!("Could not write GIF to {}: {}", path.display(), err);
+            return None;
+        }
+    }
+    Some(export)
+}
+
+/// Render a frame when due, stops exporting after an error
+fn observe_frame(export: &mut Option<FrameExport>, world: &World) {
+    if let Some(active) = export.as_mut() {
+        if let Err(err) = active.observe(world) {
+            eprintln!("Could not write frame, stopping export: {}", err);
+            *export = None;
+        }
+    }
+}
+
+/// Start streaming to browsers when asked for
+fn serve(options: &Options) -> Option<Server> {
+    let port = options.serve?;
+    match Server::start(port) {
+        Ok(server) => {
+            println!("Watch the run at http://127.0.0.1:{}", port);
+            Some(server)
+        }
+        Err(err) => {
+            eprintln!("Could not listen on port {}: {}", port, err);
+            None
+        }
+    }
+}
+
+/// Open the event log when one is asked for
+fn event_log(options: &Options) -> Option<BufWriter<File>> {
+    let path = options.event_log.as_ref()?;
+    match File::create(path) {
+        Ok(file) => Some(BufWriter::new(file)),
+        Err(err) => {
+            eprintln!("Could not write events to {}: {}", path.display(), err);
+            None
+        }
+    }
+}
+
+/// Write the events of the last step to the log, one per line,
+/// stops logging after an error
+fn log_events(log: &mut Option<BufWriter<File>>, world: &mut World) {
+    let events = world.events.drain();
+    if events.is_empty() {
+        return;
+    }
+    if let Some(active) = log.as_mut() {
+        let units = world.settings.units.as_ref();
+        let written = events
+            .iter()
+            .try_for_each(|event| writeln!(active, "{} {}", event.kind(), event.describe(units)))
+            .and_then(|()| active.flush());
+        if let Err(err) = written {
+            eprintln!("Could not write event, stopping the log: {}", err);
+            *log = None;
+        }
+    }
+}
+
+fn print_summary(world: &World) {
+    println!(
+        "Ran {} steps, t={}, {} bodies, energy drift {:+.3e}",
+        world.steps,
+        units::show(world.settings.units.as_ref(), world.time, Dimension::Time),
+        world.planets.len(),
+        world.energy_drift()
+    );
+}
+
+fn save_snapshot(options: &Options, world: &World) {
+    if let Some(path) = &options.save {
+        match Snapshot::capture(world).save(path) {
+            Ok(()) => println!("Saved snapshot to {}", path.display()),
+            Err(err) => eprintln!("Could not save snapshot to {}: {}", path.display(), err),
+        }
+    }
+}
+
+fn save_svg(options: &Options, world: &World) {
+    if let Some(path) = &options.svg {
+        let mut svg = SvgRenderer::new([world.centre[0] * 2.0, world.centre[1] * 2.0]);
+        renderer::draw_world(world, options.colour_by, &options.field, &mut svg);
+        match svg.save(path) {
+            Ok(()) => println!("Saved frame to {}", path.display()),
+            Err(err) => eprintln!("Could not save frame to {}: {}", path.display(), err),
+        }
+    }
+}
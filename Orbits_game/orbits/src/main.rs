@@ -1,34 +1,117 @@
 mod celestial_bodies;
+mod cli;
+mod game;
+mod headless;
+mod render;
+mod server;
+mod simulation;
+mod sweep;
+mod ui;
 mod utils;
 
 use celestial_bodies::body_config::*;
+use celestial_bodies::generator::BeltConfig;
 use celestial_bodies::planet::Planet;
+use game::level::{Level, LevelStart};
+use game::mission::{Mission, Outcome};
+use game::progress::Progress;
+use game::ship::{Ship, ShipState};
 use glutin_window::GlutinWindow;
 use opengl_graphics::{GlGraphics, OpenGL};
 use piston::event_loop::{EventSettings, Events};
-use piston::input::{RenderEvent, UpdateEvent};
+use piston::input::{
+    Button, Key, MouseButton, MouseCursorEvent, PressEvent, ReleaseEvent, RenderEvent, UpdateEvent,
+};
 use piston::WindowSettings;
-use utils::colour::Colour;
-use utils::physics::grav_force;
+use rand::SeedableRng;
+use render::draw_list::DrawList;
+use render::encoding::ColourEncoding;
+use render::field_overlay::FieldOverlay;
+use render::gl::GlRenderer;
+use render::renderer;
+use render::svg::SvgRenderer;
+use simulation::action::Action;
+use simulation::ephemeris::{Ephemeris, Projection};
+use simulation::lyapunov::LyapunovEstimator;
+use simulation::precession::PeriapsisTracker;
+use simulation::recording::{Recorder, Recording, Replay};
+use simulation::script::Script;
+use simulation::settings::SimSettings;
+use simulation::snapshot::Snapshot;
+use simulation::timeline::Timeline;
+use simulation::units::{UnitStyle, UnitSystem};
+use simulation::world::{SimRng, World};
+use std::path::Path;
+use std::process;
+use std::rc::Rc;
+use ui::hud::Hud;
+use ui::inspector::Inspector;
+use ui::scrubber::Scrubber;
+use ui::text;
+use ui::toasts::Toasts;
+use utils::colour::{self, Palette};
 
-const WHITE: Colour = [1.0; 4];
-const BLACK: Colour = [0.0, 0.0, 0.0, 1.0];
-const RED: Colour = [1.0, 0.0, 0.0, 1.0];
-const GRAV_CONST: f64 = 1.0;
+// Forces are integrated over dt, at the default 120 updates
+// per second this matches the old per-update force of G = 1.0
+const GRAV_CONST: f64 = 120.0;
+// Speed of light used when the 1PN correction is switched on
+const SPEED_OF_LIGHT: f64 = 500.0;
+// Range the time scale can be halved/doubled within
+const MIN_TIME_SCALE: f64 = 1.0 / 16.0;
+const MAX_TIME_SCALE: f64 = 16.0;
+// File used by the save (F5) and load (F9) hotkeys
+const SNAPSHOT_PATH: &str = "snapshot.json";
+// File used by the record (F6) and replay (F7) hotkeys
+const RECORDING_PATH: &str = "recording.bin";
+// File the current frame is exported to with F8
+const SVG_PATH: &str = "frame.svg";
+// File the results of the levels played are kept in
+const PROGRESS_PATH: &str = "progress.json";
+// Keyframes for rewinding, every half second for the last two minutes
+const KEYFRAME_INTERVAL: u64 = 60;
+const KEYFRAME_CAPACITY: usize = 240;
+// Mass of planets spawned with the right mouse button
+const SPAWN_MASS: f64 = 10.0;
+// Radius of the orbit the ship starts on and its delta-v budget
+const SHIP_ORBIT: f64 = 150.0;
+const SHIP_FUEL: f64 = 150.0;
+// Colour of the Lyapunov exponent panel
+const LYAPUNOV_TEXT: colour::Colour = [1.0, 0.8, 0.4, 1.0];
+const SHIP_TEXT: colour::Colour = [0.4, 0.9, 1.0, 1.0];
+
+/// Configuration of the small planets
+fn planet_config(bounds: f64) -> PlanetConfig {
+    PlanetConfig::new(0.0, bounds, 50.0, 10.0, 5.0, 1.0)
+}
+
+/// Configuration of the heavy body near the centre
+fn primary_config(bounds: f64) -> PlanetConfig {
+    PlanetConfig::new(bounds * 0.45, bounds * 0.55, 0.001, 7000.0, 5.0, 10.0)
+}
 
 // Some constants used throughout the code
 // Create a set of planets according to
-// a normal distribution
-fn create_planets(amt_planet: u32, bounds: f64) -> Vec<Planet> {
+// a normal distribution, each group gets the
+// next colour of the palette
+fn create_planets(
+    amt_planet: u32,
+    planet_const: &PlanetConfig,
+    primary_const: &PlanetConfig,
+    palette: Palette,
+    rng: &mut SimRng,
+) -> Vec<Planet> {
     //(Planet, Vec<Planet>) {
     let mut planets = Vec::<Planet>::new();
-    let planet_const = PlanetConfig::new(0.0, bounds, 50.0, 10.0, 5.0, 0.2);
     for i in 0..amt_planet {
-        planets.push(Planet::new(&planet_const, i, WHITE));
+        planets.push(Planet::new(planet_const, i, palette.colour(0), rng));
     }
-    let planet_const = PlanetConfig::new(bounds * 0.45, bounds * 0.55, 0.001, 7000.0, 5.0, 0.001);
     for i in 0..1 {
-        planets.push(Planet::new(&planet_const, i, RED));
+        planets.push(Planet::new(
+            primary_const,
+            amt_planet + i,
+            palette.colour(1),
+            rng,
+        ));
     }
 
     planets
@@ -36,55 +119,603 @@ fn create_planets(amt_planet: u32, bounds: f64) -> Vec<Planet> {
 
 fn main() {
     // SET UP THE MAIN CONFIG DATA
+    let options = cli::Options::from_args();
     let bounds: f64 = 1028.0; // window size
+
+    if let Some(path) = &options.replay {
+        let recording = or_exit(
+            Recording::load(path)
+                .map_err(|err| format!("Could not load recording {}: {}", path.display(), err)),
+        );
+        let (replay, world) = Replay::start(recording);
+        if options.headless {
+            headless::run_replay(&options, replay, world);
+        } else {
+            let ship = launch_ship(&options, &world);
+            run_window(world, bounds, Some(replay), ship, None, None);
+        }
+        return;
+    }
+
+    if !options.sweep.is_empty() {
+        sweep::run(&options, |run| {
+            let (mut planet_const, mut primary_const) =
+                (planet_config(bounds), primary_config(bounds));
+            run.configure(&options.sweep, &mut planet_const, &mut primary_const)?;
+            let mut world =
+                generate_world(&options, bounds, run.seed, &planet_const, &primary_const)?;
+            apply_model_options(&mut world, &options);
+            Ok(world)
+        });
+        return;
+    }
+
+    if let Some(path) = &options.level {
+        let level = or_exit(
+            Level::load(path)
+                .map_err(|err| format!("Could not load level {}: {}", path.display(), err)),
+        );
+        println!("{}: {}", level.name, level.description);
+        match Progress::load(PROGRESS_PATH).map(|progress| progress.get(&level.name).cloned()) {
+            Ok(Some(progress)) => println!(
+                "Played {} times, won {}, best score {}",
+                progress.attempts,
+                progress.wins,
+                progress
+                    .best_score
+                    .map_or("none".to_string(), |score| score.to_string())
+            ),
+            Ok(None) => {}
+            Err(err) => eprintln!("Could not read progress: {}", err),
+        }
+        let start = or_exit(start_level(&level, bounds));
+        run_window(
+            start.world,
+            bounds,
+            None,
+            Some(start.ship),
+            Some((level, start.mission)),
+            None,
+        );
+        return;
+    }
+
+    let mut world = or_exit(create_world(&options, bounds));
+    let script = options
+        .script
+        .as_ref()
+        .map(|path| or_exit(load_script(path, &mut world, bounds)));
+    if options.headless {
+        headless::run(&options, world, script.as_deref());
+    } else {
+        let ship = launch_ship(&options, &world);
+        run_window(world, bounds, None, ship, None, script);
+    }
+}
+
+/// The value of a step of the set up, or the end of
+/// the program with its error when the step failed
+fn or_exit<T>(result: Result<T, String>) -> T {
+    result.unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        process::exit(1);
+    })
+}
+
+/// Load the script and let it set the world up
+fn load_script(path: &Path, world: &mut World, bounds: f64) -> Result<Rc<Script>, String> {
+    let script = Script::load(path, &planet_config(bounds))
+        .map_err(|err| format!("Could not load script {}: {}", path.display(), err))?;
+    script
+        .setup(world)
+        .map_err(|err| format!("Could not run script {}: {}", path.display(), err))?;
+    Ok(script)
+}
+
+/// Set up a level in the middle of the window
+fn start_level(level: &Level, bounds: f64) -> Result<LevelStart, String> {
+    level
+        .start(
+            [bounds * 0.5, bounds * 0.5],
+            GRAV_CONST,
+            &planet_config(bounds),
+        )
+        .map_err(|err| format!("Could not start level {}: {}", level.name, err))
+}
+
+/// Count a finished attempt at a level in the progress file
+fn record_progress(level: &Level, outcome: &Outcome) {
+    let score = match outcome {
+        Outcome::Won { score } => Some(*score),
+        Outcome::Lost { .. } => None,
+    };
+    let saved = Progress::load(PROGRESS_PATH).and_then(|mut progress| {
+        progress.record(&level.name, score);
+        progress.save(PROGRESS_PATH)
+    });
+    if let Err(err) = saved {
+        eprintln!("Could not save progress: {}", err);
+    }
+}
+
+/// The player's ship, when playing
+fn launch_ship(options: &cli::Options, world: &World) -> Option<Ship> {
+    if !options.ship {
+        return None;
+    }
+    Ship::launch(world, SHIP_ORBIT, SHIP_FUEL)
+}
+
+/// Either load the world from a snapshot or generate
+/// a new one from the (given or random) seed
+fn create_world(options: &cli::Options, bounds: f64) -> Result<World, String> {
+    let mut world = match &options.load {
+        Some(path) => Snapshot::load(path)
+            .map_err(|err| format!("Could not load snapshot {}: {}", path.display(), err))?
+            .restore(),
+        None => {
+            let seed = options.seed.unwrap_or_else(rand::random);
+            println!("Seed: {}", seed);
+            generate_world(
+                options,
+                bounds,
+                seed,
+                &planet_config(bounds),
+                &primary_config(bounds),
+            )?
+        }
+    };
+    apply_model_options(&mut world, options);
+    Ok(world)
+}
+
+/// Generate a world from the seed, the bodies follow the configurations
+/// unless they are imported from an ephemeris
+fn generate_world(
+    options: &cli::Options,
+    bounds: f64,
+    seed: u64,
+    planet_const: &PlanetConfig,
+    primary_const: &PlanetConfig,
+) -> Result<World, String> {
+    let mut rng = SimRng::seed_from_u64(seed);
     let centre: [f64; 2] = [bounds * 0.5, bounds * 0.5];
-    let mut planets = create_planets(15, bounds);
+    let mut settings = SimSettings::new(GRAV_CONST);
+    let mut planets = match &options.ephemeris {
+        Some(path) => {
+            // Real state vectors need real units
+            let style = options.units.unwrap_or(UnitStyle::Astronomical);
+            let units = UnitSystem::solar(GRAV_CONST, style);
+            settings.units = Some(units);
+            import_ephemeris(path, &units, options.projection, centre, bounds)?
+        }
+        None => create_planets(15, planet_const, primary_const, options.palette, &mut rng),
+    };
+    add_belts(&mut planets, options, bounds, &mut rng);
+    Ok(World::new(planets, centre, settings, rng))
+}
+
+/// Switch on the models asked for on the command line. Only switches
+/// them on, a loaded snapshot keeps its own settings otherwise
+fn apply_model_options(world: &mut World, options: &cli::Options) {
+    world.settings.tidal_disruption |= options.tides;
+    world.settings.collisions |= options.collisions;
+    if let Some(style) = options.units {
+        world.settings.units = Some(UnitSystem::solar(world.settings.grav_const, style));
+    }
+    if let Some(distance) = options.close_approach {
+        world.detector.close_approach = distance;
+    }
+}
+
+/// Read the bodies from an ephemeris file
+fn import_ephemeris(
+    path: &Path,
+    units: &UnitSystem,
+    projection: Projection,
+    centre: [f64; 2],
+    bounds: f64,
+) -> Result<Vec<Planet>, String> {
+    let ephemeris = Ephemeris::load(path)
+        .map_err(|err| format!("Could not import ephemeris {}: {}", path.display(), err))?;
+    if !ephemeris.same_epoch() {
+        eprintln!(
+            "Warning: the bodies in {} are not all at the same epoch",
+            path.display()
+        );
+    }
+    let names: Vec<&str> = ephemeris
+        .bodies
+        .iter()
+        .map(|body| body.name.as_str())
+        .collect();
+    println!("Imported {} from {}", names.join(", "), path.display());
+    // Bodies are as dense as the heavy body of the default world
+    let planet_const = PlanetConfig::new(0.0, bounds, 50.0, 10.0, 5.0, 10.0);
+    let planets = ephemeris.to_planets(units, projection, &planet_const, centre, 0);
+    let outside: Vec<&str> = names
+        .iter()
+        .zip(planets.iter())
+        .filter(|(_, planet)| planet.is_out_of_bounds(centre))
+        .map(|(name, _)| *name)
+        .collect();
+    if !outside.is_empty() {
+        eprintln!(
+            "Warning: {} start{} beyond the boundary and will be put back at random",
+            outside.join(", "),
+            if outside.len() == 1 { "s" } else { "" }
+        );
+    }
+    Ok(planets)
+}
+
+/// Surround the heaviest body with the belt and disk asked for on
+/// the command line, in the next two colours of the palette
+fn add_belts(planets: &mut Vec<Planet>, options: &cli::Options, bounds: f64, rng: &mut SimRng) {
+    let belts = [
+        options.belt.map(BeltConfig::asteroid_belt),
+        options.disk.map(BeltConfig::accretion_disk),
+    ];
+    for (i, belt) in belts.iter().enumerate() {
+        let belt = match belt {
+            Some(belt) => belt,
+            None => continue,
+        };
+        let primary = match planets.iter().max_by(|a, b| a.mass().total_cmp(&b.mass())) {
+            Some(primary) => primary.clone(),
+            None => return,
+        };
+        let first_id = planets
+            .iter()
+            .map(|planet| planet.id() + 1)
+            .max()
+            .unwrap_or(0);
+        let colour = options.palette.colour(2 + i);
+        planets.extend(belt.generate(
+            &planet_config(bounds),
+            &primary,
+            GRAV_CONST,
+            first_id,
+            colour,
+            rng,
+        ));
+    }
+}
+
+/// A new planet at rest at the given position
+fn spawn_planet(world: &World, pos: [f64; 2], bounds: f64) -> Planet {
+    Planet::from_state(
+        &planet_config(bounds),
+        world.next_id(),
+        colour::WHITE,
+        pos,
+        [0.0, 0.0],
+        SPAWN_MASS,
+    )
+}
+
+/// Open a window and run the simulation in it until it is closed,
+/// following the replay if one is given and flying the ship if there is one.
+/// With a level, the ship plays its mission. A script is called after every step
+fn run_window(
+    mut world: World,
+    bounds: f64,
+    mut replay: Option<Replay>,
+    mut ship: Option<Ship>,
+    mut level: Option<(Level, Mission)>,
+    script: Option<Rc<Script>>,
+) {
+    // Follow the first planet around the heaviest body to measure the
+    // precession of its periapsis, a script may have left no bodies at all
+    let mut tracker = world
+        .heaviest()
+        .map(|primary| PeriapsisTracker::new(world.planets[0].id(), world.planets[primary].id()));
+    let mut inspector = Inspector::new();
+    let mut hud = Hud::new();
+    let mut recorder: Option<Recorder> = None;
+    let mut timeline = Timeline::new(KEYFRAME_INTERVAL, KEYFRAME_CAPACITY);
+    let mut scrubber = Scrubber::new(bounds);
+    let mut cursor: [f64; 2] = [0.0, 0.0];
+    let mut encoding = ColourEncoding::Group;
+    let mut field = FieldOverlay::new();
+    let mut lyapunov: Option<LyapunovEstimator> = None;
+    let mut toasts = Toasts::new();
+    // A level is played as it was set up, without edits or jumps in time
+    let playing = level.is_some();
 
     let opengl = OpenGL::V3_2;
-    let settings = WindowSettings::new("Window", [bounds; 2]).exit_on_esc(true);
+    let settings = WindowSettings::new("Orbits", [bounds; 2]).exit_on_esc(true);
     let mut window: GlutinWindow = settings.build().expect("Could not create window");
     let mut gl = GlGraphics::new(opengl);
+    let mut glyphs = render::gl::load_font();
+    // Primitives of the last frame, kept around for the SVG export
+    let mut frame = DrawList::new();
     let mut events = Events::new(EventSettings::new());
 
     // Game loop. First, render every object (planet),
     // then, update each planet's position and check
     // for collisions.
     while let Some(e) = events.next(&mut window) {
-        // Render step, all planetary bodies
+        // Changes to the world made by the user in response to this event
+        let mut actions: Vec<Action> = Vec::new();
+        // World to show when scrubbing through the timeline
+        let mut rewound: Option<World> = None;
+        // Whether a snapshot or a recording took the place of the world
+        let mut jumped = false;
+
+        if let Some(pos) = e.mouse_cursor_args() {
+            cursor = pos;
+            if scrubber.dragging {
+                rewound = timeline
+                    .scrub_to(scrubber.fraction_at(cursor))
+                    .map(Snapshot::restore);
+            }
+        }
+        match e.release_args() {
+            Some(Button::Mouse(MouseButton::Left)) => scrubber.dragging = false,
+            Some(Button::Keyboard(key)) => {
+                if let Some(ship) = ship.as_mut() {
+                    ship.controls.handle_key(key, false);
+                }
+            }
+            _ => {}
+        }
+        match e.press_args() {
+            Some(Button::Mouse(MouseButton::Left)) if scrubber.contains(cursor) && !playing => {
+                scrubber.dragging = true;
+                rewound = timeline
+                    .scrub_to(scrubber.fraction_at(cursor))
+                    .map(Snapshot::restore);
+            }
+            Some(Button::Mouse(MouseButton::Left)) => inspector.select_at(&world, cursor),
+            Some(Button::Mouse(MouseButton::Right)) if !playing => {
+                actions.push(Action::Spawn(spawn_planet(&world, cursor, bounds)))
+            }
+            _ => {}
+        }
+
+        if let Some(Button::Keyboard(key)) = e.press_args() {
+            // Keys that edit the selected body take precedence
+            if let Some(action) = inspector.handle_key(key, &world).filter(|_| !playing) {
+                if let Action::Remove { .. } = action {
+                    inspector.selected = None;
+                }
+                actions.push(action);
+            }
+            let steered = ship
+                .as_mut()
+                .is_some_and(|ship| ship.controls.handle_key(key, true));
+            match key {
+                _ if !actions.is_empty() || steered => {}
+                Key::R | Key::T | Key::K | Key::I if playing => {}
+                Key::LeftBracket | Key::RightBracket | Key::F6 | Key::F7 | Key::F9 if playing => {}
+                // Start the level over
+                Key::Return if level.is_some() => {
+                    if let Some((level, mission)) = level.as_mut() {
+                        match start_level(level, bounds) {
+                            Ok(start) => {
+                                world = start.world;
+                                ship = Some(start.ship);
+                                *mission = start.mission;
+                                recorder = None;
+                                timeline.clear();
+                            }
+                            Err(err) => toasts.show(err),
+                        }
+                    }
+                }
+                // Start over with a fresh ship after a crash
+                Key::Return if ship.is_some() => {
+                    ship = Ship::launch(&world, SHIP_ORBIT, SHIP_FUEL);
+                }
+                // Toggle the relativistic (1PN) correction
+                Key::R => actions.push(Action::SetSpeedOfLight(
+                    match world.settings.speed_of_light {
+                        Some(_) => None,
+                        None => Some(SPEED_OF_LIGHT),
+                    },
+                )),
+                Key::T => {
+                    actions.push(Action::SetTidalDisruption(!world.settings.tidal_disruption))
+                }
+                Key::K => actions.push(Action::SetCollisions(!world.settings.collisions)),
+                // Cycle the inspected body and the primary it orbits
+                Key::Tab => inspector.select_next(&world),
+                Key::P => inspector.primary_next(&world),
+                Key::H => hud.visible = !hud.visible,
+                Key::C => encoding = encoding.next(),
+                Key::F => field.arrows = !field.arrows,
+                Key::G => field.potential = !field.potential,
+                Key::N => toasts.visible = !toasts.visible,
+                Key::L => {
+                    lyapunov = match lyapunov {
+                        Some(_) => None,
+                        None => Some(LyapunovEstimator::start(&world)),
+                    }
+                }
+                Key::I => actions.push(Action::SetIntegrator(world.settings.integrator.next())),
+                Key::Comma => actions.push(Action::SetTimeScale(
+                    (world.settings.time_scale * 0.5).max(MIN_TIME_SCALE),
+                )),
+                Key::Period => actions.push(Action::SetTimeScale(
+                    (world.settings.time_scale * 2.0).min(MAX_TIME_SCALE),
+                )),
+                // Scrub through the timeline, and continue from the shown keyframe
+                Key::LeftBracket => rewound = timeline.scrub(-1).map(Snapshot::restore),
+                Key::RightBracket => rewound = timeline.scrub(1).map(Snapshot::restore),
+                Key::Space => timeline.resume(),
+                Key::F5 => match Snapshot::capture(&world).save(SNAPSHOT_PATH) {
+                    Ok(()) => println!("Saved snapshot to {}", SNAPSHOT_PATH),
+                    Err(err) => eprintln!("Could not save snapshot: {}", err),
+                },
+                Key::F9 => match Snapshot::load(SNAPSHOT_PATH) {
+                    Ok(snapshot) => {
+                        world.replace(snapshot.restore());
+                        jumped = true;
+                        // A recording can't continue across a jump in state
+                        recorder = None;
+                        replay = None;
+                        timeline.clear();
+                        println!("Loaded snapshot from {}", SNAPSHOT_PATH);
+                    }
+                    Err(err) => eprintln!("Could not load snapshot: {}", err),
+                },
+                Key::F8 => {
+                    let mut svg = SvgRenderer::new([bounds; 2]);
+                    frame.replay(&mut svg);
+                    match svg.save(SVG_PATH) {
+                        Ok(()) => println!("Saved frame to {}", SVG_PATH),
+                        Err(err) => eprintln!("Could not save frame: {}", err),
+                    }
+                }
+                Key::F6 => match recorder.take() {
+                    Some(active) => match active.finish(&world).save(RECORDING_PATH) {
+                        Ok(()) => println!("Saved recording to {}", RECORDING_PATH),
+                        Err(err) => eprintln!("Could not save recording: {}", err),
+                    },
+                    // The changes a script makes are not recorded
+                    None if script.is_some() => {
+                        toasts.show("Runs with a script can't be recorded".to_string())
+                    }
+                    None if replay.is_none() => {
+                        recorder = Some(Recorder::start(&world));
+                        println!("Recording started");
+                    }
+                    None => {}
+                },
+                Key::F7 => match Recording::load(RECORDING_PATH) {
+                    Ok(recording) => {
+                        let (started, replay_world) = Replay::start(recording);
+                        world.replace(replay_world);
+                        jumped = true;
+                        recorder = None;
+                        replay = Some(started);
+                        timeline.clear();
+                        println!("Replaying {}", RECORDING_PATH);
+                    }
+                    Err(err) => eprintln!("Could not load recording: {}", err),
+                },
+                _ => {}
+            }
+        }
+
+        let changed = jumped || rewound.is_some() || !actions.is_empty();
+        if let Some(keyframe) = rewound {
+            world.replace(keyframe);
+            recorder = None;
+            replay = None;
+        }
+
+        if let (Some(ship), true) = (ship.as_mut(), changed) {
+            ship.invalidate_prediction();
+        }
+
+        // During a replay the world only follows the recording
+        if replay.is_none() {
+            let edited = !actions.is_empty();
+            for action in actions {
+                if let Some(active) = recorder.as_mut() {
+                    active.record(&world, &action);
+                }
+                action.apply(&mut world);
+            }
+            // An edit moves the world away from its shadow, so measure anew
+            if let (Some(estimator), true) = (lyapunov.as_mut(), edited) {
+                *estimator = LyapunovEstimator::start(&world);
+            }
+        }
+
+        // Render step, all planetary bodies and the overlays on top
         if let Some(r) = e.render_args() {
-            gl.draw(r.viewport(), |c: graphics::Context, g: &mut GlGraphics| {
-                graphics::clear(BLACK, g);
-                for planet in planets.iter() {
-                    planet.draw(c, g);
+            hud.frame();
+            renderer::draw_world(&world, encoding, &field, &mut frame);
+            encoding.draw_legend(&world, [bounds - 180.0, bounds - 70.0], &mut frame);
+            if !playing {
+                scrubber.draw(&timeline, &mut frame);
+            }
+            let hud_height = hud.draw(&world, [10.0, 10.0], &mut frame);
+            inspector.draw(&world, [10.0, hud_height + 20.0], &mut frame);
+            if let Some(estimator) = &lyapunov {
+                text::draw_panel(
+                    &estimator.lines(&world),
+                    [bounds - 260.0, 10.0],
+                    LYAPUNOV_TEXT,
+                    &mut frame,
+                );
+            }
+            if let Some(ship) = &ship {
+                ship.draw(&mut frame);
+                let mut lines = ship.lines(world.settings.units.as_ref());
+                if let Some((_, mission)) = &level {
+                    lines.extend(mission.lines(world.settings.units.as_ref()));
                 }
+                text::draw_panel(&lines, [bounds - 260.0, 100.0], SHIP_TEXT, &mut frame);
+            }
+            toasts.draw([bounds * 0.5 - 150.0, 10.0], &mut frame);
+
+            gl.draw(r.viewport(), |c: graphics::Context, g: &mut GlGraphics| {
+                frame.replay(&mut GlRenderer::new(c, g, &mut glyphs));
             });
         }
 
         // Update step, each planet moves by its vel
-        // scaled by dt for a frame-independent movement
-        if let Some(args) = e.update_args() {
-            // Handle step-wise update of each planet
-            for planet in planets.iter_mut() {
-                planet.update(&args); // pass update args for 'dt' value to scale movement
-                planet.check_dist_from_centre(centre);
-            }
-
-            let mut bottom: usize = 1;
-            // Handle gravitational force for unique planet pair
-            // e.g. for 5 planets we have 5+4+3+2+1=15 force calcs
-            for i in 0..planets.len() {
-                for j in bottom..planets.len() {
-                    if i != j {
-                        // Obtain force, it is always equal and opposite,
-                        // the .add_force method scales the force by the mass
-                        // of the body
-                        let (force, force_inv) = grav_force(&planets[i], &planets[j], GRAV_CONST);
-                        planets[i].add_force(force_inv);
-                        planets[j].add_force(force);
+        // scaled by dt for a frame-independent movement.
+        // The simulation is paused while scrubbing
+        if e.update_args().is_some() && !timeline.is_scrubbing() {
+            if let Some(active) = replay.as_mut() {
+                active.apply_due(&mut world);
+                if active.is_finished(&world) {
+                    println!(
+                        "Replay finished, matches recording: {}",
+                        active.matches(&world)
+                    );
+                    replay = None;
+                }
+            }
+            world.advance();
+            if let Some(script) = &script {
+                script.step(&mut world);
+            }
+            timeline.observe(&world);
+            if let Some(estimator) = lyapunov.as_mut() {
+                estimator.observe(&world);
+            }
+            for event in world.events.drain() {
+                toasts.push(&event, world.settings.units.as_ref());
+            }
+            if let Some(ship) = ship.as_mut() {
+                match ship.step(&world) {
+                    Some(ShipState::Landed { on }) => {
+                        toasts.show(format!("Ship landed on #{}", on))
+                    }
+                    Some(ShipState::Crashed { into }) => {
+                        toasts.show(format!("Ship crashed into #{}, Enter to relaunch", into))
+                    }
+                    Some(_) => toasts.show("Ship lost in space, Enter to relaunch".to_string()),
+                    None => {}
+                }
+                ship.predict(&world);
+
+                if let Some((level, mission)) = level.as_mut() {
+                    match mission.update(&world, ship) {
+                        Some(Outcome::Won { score }) => {
+                            toasts.show(format!("Mission complete, score {}", score));
+                            record_progress(level, &Outcome::Won { score });
+                        }
+                        Some(outcome) => {
+                            toasts.show("Mission failed, Enter to try again".to_string());
+                            record_progress(level, &outcome);
+                        }
+                        None => {}
                     }
                 }
+            }
 
-                bottom += 1;
+            if let Some(sample) = tracker.as_mut().and_then(|tracker| tracker.observe(&world)) {
+                println!(
+                    "t={:.2} periapsis advance: {:.5} rad/orbit (1PN prediction: {:?})",
+                    sample.time, sample.advance, sample.predicted
+                );
             }
         }
     }
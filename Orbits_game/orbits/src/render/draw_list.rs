@@ -0,0 +1,157 @@
+use super::renderer::Renderer;
+use crate::utils::colour::Colour;
+
+/// A single recorded drawing call
+#[derive(Debug, Clone, PartialEq)]
+pub enum Primitive {
+    Clear(Colour),
+    Rectangle {
+        colour: Colour,
+        rect: [f64; 4],
+    },
+    Circle {
+        colour: Colour,
+        centre: [f64; 2],
+        radius: f64,
+    },
+    Line {
+        colour: Colour,
+        radius: f64,
+        line: [f64; 4],
+    },
+    Text {
+        colour: Colour,
+        size: u32,
+        pos: [f64; 2],
+        text: String,
+    },
+}
+
+/// Renderer that records the primitives it is given, so a frame
+/// can be drawn once and replayed into one or more backends
+#[derive(Debug, Clone, Default)]
+pub struct DrawList {
+    primitives: Vec<Primitive>,
+}
+
+impl DrawList {
+    pub fn new() -> DrawList {
+        DrawList {
+            primitives: Vec::new(),
+        }
+    }
+
+    /// The primitives recorded since the last clear, in order
+    // Only read by the tests so far, to check what a frame draws
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub fn primitives(&self) -> &[Primitive] {
+        &self.primitives
+    }
+
+    /// Draw the recorded primitives, in order, with another renderer
+    pub fn replay<R: Renderer>(&self, renderer: &mut R) {
+        for primitive in self.primitives.iter() {
+            match primitive {
+                Primitive::Clear(colour) => renderer.clear(*colour),
+                Primitive::Rectangle { colour, rect } => renderer.rectangle(*colour, *rect),
+                Primitive::Circle {
+                    colour,
+                    centre,
+                    radius,
+                } => renderer.circle(*colour, *centre, *radius),
+                Primitive::Line {
+                    colour,
+                    radius,
+                    line,
+                } => renderer.line(*colour, *radius, *line),
+                Primitive::Text {
+                    colour,
+                    size,
+                    pos,
+                    text,
+                } => renderer.text(*colour, *size, *pos, text),
+            }
+        }
+    }
+}
+
+impl Renderer for DrawList {
+    fn clear(&mut self, colour: Colour) {
+        // Anything before a clear would be painted over anyway
+        self.primitives.clear();
+        self.primitives.push(Primitive::Clear(colour));
+    }
+
+    fn rectangle(&mut self, colour: Colour, rect: [f64; 4]) {
+        self.primitives.push(Primitive::Rectangle { colour, rect });
+    }
+
+    fn circle(&mut self, colour: Colour, centre: [f64; 2], radius: f64) {
+        self.primitives.push(Primitive::Circle {
+            colour,
+            centre,
+            radius,
+        });
+    }
+
+    fn line(&mut self, colour: Colour, radius: f64, line: [f64; 4]) {
+        self.primitives.push(Primitive::Line {
+            colour,
+            radius,
+            line,
+        });
+    }
+
+    fn text(&mut self, colour: Colour, size: u32, pos: [f64; 2], text: &str) {
+        self.primitives.push(Primitive::Text {
+            colour,
+            size,
+            pos,
+            text: text.to_string(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::celestial_bodies::body_config::PlanetConfig;
+    use crate::celestial_bodies::planet::Planet;
+    use crate::utils::colour;
+
+    #[test]
+    fn planets_are_recorded_as_circles() {
+        let config = PlanetConfig::new(0.0, 800.0, 0.0, 1.0, 0.0, 1.0);
+        let heavy = Planet::from_state(&config, 0, colour::WHITE, [10.0, 20.0], [0.0; 2], 1000.0);
+        let tiny = Planet::from_state(&config, 1, colour::WHITE, [30.0, 40.0], [0.0; 2], 0.001);
+        let mut list = DrawList::new();
+        list.line(colour::RED, 1.0, [0.0, 0.0, 1.0, 1.0]);
+        list.clear(colour::BLACK);
+        heavy.draw(&mut list, colour::RED);
+        tiny.draw(&mut list, colour::WHITE);
+
+        // The clear dropped the line drawn before it
+        let radius = (3.0 * 1000.0 / (4.0 * std::f64::consts::PI)).cbrt();
+        assert_eq!(
+            list.primitives(),
+            [
+                Primitive::Clear(colour::BLACK),
+                Primitive::Circle {
+                    colour: colour::RED,
+                    centre: [10.0, 20.0],
+                    radius
+                },
+                // Too small to see, so drawn at the smallest size
+                Primitive::Circle {
+                    colour: colour::WHITE,
+                    centre: [30.0, 40.0],
+                    radius: 1.0
+                },
+            ]
+        );
+
+        let mut copy = DrawList::new();
+        list.replay(&mut copy);
+        assert_eq!(copy.primitives(), list.primitives());
+    }
+}
@@ -0,0 +1,155 @@
+use super::renderer::Renderer;
+use crate::celestial_bodies::body_config::CelestialBody;
+use crate::celestial_bodies::planet::Planet;
+use crate::simulation::world::World;
+use crate::utils::array_logic::Length;
+use crate::utils::colour::{self, Colour, Gradient};
+
+const LEGEND_TEXT: Colour = colour::WHITE;
+const LEGEND_STEPS: usize = 32;
+const LEGEND_SIZE: [f64; 2] = [160.0, 10.0];
+
+/// What the colour of a body shows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColourEncoding {
+    /// The colour the body was created with
+    Group,
+    /// A distinct colour for every body id
+    Id,
+    Speed,
+    KineticEnergy,
+    Mass,
+}
+
+impl ColourEncoding {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ColourEncoding::Group => "group",
+            ColourEncoding::Id => "id",
+            ColourEncoding::Speed => "speed",
+            ColourEncoding::KineticEnergy => "kinetic energy",
+            ColourEncoding::Mass => "mass",
+        }
+    }
+
+    /// The next encoding in the list, wrapping around
+    pub fn next(&self) -> ColourEncoding {
+        match self {
+            ColourEncoding::Group => ColourEncoding::Id,
+            ColourEncoding::Id => ColourEncoding::Speed,
+            ColourEncoding::Speed => ColourEncoding::KineticEnergy,
+            ColourEncoding::KineticEnergy => ColourEncoding::Mass,
+            ColourEncoding::Mass => ColourEncoding::Group,
+        }
+    }
+
+    /// Parse an encoding from its short name, e.g. on the command line
+    pub fn from_name(name: &str) -> Option<ColourEncoding> {
+        match name {
+            "group" => Some(ColourEncoding::Group),
+            "id" => Some(ColourEncoding::Id),
+            "speed" => Some(ColourEncoding::Speed),
+            "energy" => Some(ColourEncoding::KineticEnergy),
+            "mass" => Some(ColourEncoding::Mass),
+            _ => None,
+        }
+    }
+
+    /// The value that is encoded, None when showing groups or ids
+    fn value(&self, planet: &Planet) -> Option<f64> {
+        match self {
+            ColourEncoding::Group | ColourEncoding::Id => None,
+            ColourEncoding::Speed => Some(planet.vel().get_length()),
+            ColourEncoding::KineticEnergy => Some(planet.kinetic_energy()),
+            ColourEncoding::Mass => Some(planet.mass()),
+        }
+    }
+
+    /// Gradient the values are shown with
+    fn gradient(&self) -> Gradient {
+        match self {
+            ColourEncoding::Speed => Gradient::Heat,
+            ColourEncoding::KineticEnergy => Gradient::Magma,
+            _ => Gradient::Viridis,
+        }
+    }
+
+    /// Energy and mass span orders of magnitude, so use a log scale
+    fn is_logarithmic(&self) -> bool {
+        matches!(self, ColourEncoding::KineticEnergy | ColourEncoding::Mass)
+    }
+
+    /// Lowest and highest value over all bodies in the world
+    pub fn range(&self, world: &World) -> Option<(f64, f64)> {
+        let mut values = world.planets.iter().filter_map(|planet| self.value(planet));
+        let first = values.next()?;
+        Some(values.fold((first, first), |(low, high), value| {
+            (low.min(value), high.max(value))
+        }))
+    }
+
+    /// Position of a value within the range, from 0 to 1
+    fn normalise(&self, value: f64, (low, high): (f64, f64)) -> f64 {
+        let (value, low, high) = if self.is_logarithmic() {
+            // Values can be zero, e.g. the energy of a body at rest
+            let log = |v: f64| v.max(1e-9).log10();
+            (log(value), log(low), log(high))
+        } else {
+            (value, low, high)
+        };
+        if high > low {
+            (value - low) / (high - low)
+        } else {
+            0.5
+        }
+    }
+
+    /// The colour every planet in the world is drawn with
+    pub fn colours(&self, world: &World) -> Vec<Colour> {
+        let range = self.range(world);
+        world
+            .planets
+            .iter()
+            .map(|planet| match (self.value(planet), range) {
+                (Some(value), Some(range)) => self.gradient().sample(self.normalise(value, range)),
+                _ if *self == ColourEncoding::Id => colour::for_id(planet.id() as u64),
+                _ => planet.colour(),
+            })
+            .collect()
+    }
+
+    /// Draw a legend of the gradient with its range,
+    /// nothing is drawn when showing groups or ids
+    pub fn draw_legend<R: Renderer>(&self, world: &World, pos: [f64; 2], renderer: &mut R) {
+        let (low, high) = match self.range(world) {
+            Some(range) => range,
+            None => return,
+        };
+        let step_width = LEGEND_SIZE[0] / LEGEND_STEPS as f64;
+        for i in 0..LEGEND_STEPS {
+            let t = i as f64 / (LEGEND_STEPS - 1) as f64;
+            let rect = [
+                pos[0] + i as f64 * step_width,
+                pos[1],
+                step_width + 0.5,
+                LEGEND_SIZE[1],
+            ];
+            renderer.rectangle(self.gradient().sample(t), rect);
+        }
+        let scale = if self.is_logarithmic() { " (log)" } else { "" };
+        renderer.text(
+            LEGEND_TEXT,
+            12,
+            [pos[0], pos[1] - 6.0],
+            &format!("{}{}", self.name(), scale),
+        );
+        let below = pos[1] + LEGEND_SIZE[1] + 14.0;
+        renderer.text(LEGEND_TEXT, 12, [pos[0], below], &format!("{:.3e}", low));
+        renderer.text(
+            LEGEND_TEXT,
+            12,
+            [pos[0] + LEGEND_SIZE[0] - 60.0, below],
+            &format!("{:.3e}", high),
+        );
+    }
+}
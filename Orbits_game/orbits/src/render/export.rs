@@ -0,0 +1,200 @@
+use super::encoding::ColourEncoding;
+use super::field_overlay::FieldOverlay;
+use super::raster::Raster;
+use super::renderer;
+use crate::simulation::world::{World, FIXED_DT};
+use std::fs::{self, File};
+use std::io::{self, BufWriter};
+use std::path::PathBuf;
+
+/// Where rendered frames end up
+enum Sink {
+    /// Numbered PNG files in a directory
+    Png { dir: PathBuf, count: u32 },
+    /// Frames of a single animated GIF
+    Gif(gif::Encoder<BufWriter<File>>),
+}
+
+/// Renders the world offscreen every `stride` steps
+/// and writes the frames out as PNGs or a GIF
+pub struct FrameExport {
+    raster: Raster,
+    sinks: Vec<Sink>,
+    stride: u64,
+    encoding: ColourEncoding,
+    field: FieldOverlay,
+}
+
+impl FrameExport {
+    /// Frames of `width` x `height` pixels showing a world area of size `view`
+    pub fn new(
+        width: u32,
+        height: u32,
+        view: [f64; 2],
+        stride: u64,
+        encoding: ColourEncoding,
+        field: FieldOverlay,
+    ) -> io::Result<FrameExport> {
+        Ok(FrameExport {
+            raster: Raster::new(width, height, view)?,
+            sinks: Vec::new(),
+            stride: stride.max(1),
+            encoding,
+            field,
+        })
+    }
+
+    /// Also write every frame as a PNG into the directory
+    pub fn add_png_sequence(&mut self, dir: PathBuf) -> io::Result<()> {
+        fs::create_dir_all(&dir)?;
+        self.sinks.push(Sink::Png { dir, count: 0 });
+        Ok(())
+    }
+
+    /// Also write every frame into an animated GIF
+    pub fn add_gif(&mut self, path: PathBuf) -> io::Result<()> {
+        let file = BufWriter::new(File::create(path)?);
+        let (width, height) = gif_size(&self.raster)?;
+        let mut encoder = gif::Encoder::new(file, width, height, &[]).map_err(to_io_error)?;
+        encoder
+            .set_repeat(gif::Repeat::Infinite)
+            .map_err(to_io_error)?;
+        self.sinks.push(Sink::Gif(encoder));
+        Ok(())
+    }
+
+    /// Render and write a frame when one is due,
+    /// call this after every step
+    pub fn observe(&mut self, world: &World) -> io::Result<()> {
+        if self.sinks.is_empty() || !world.steps.is_multiple_of(self.stride) {
+            return Ok(());
+        }
+        renderer::draw_world(world, self.encoding, &self.field, &mut self.raster);
+        // GIF delays are in hundredths of a second of simulated time
+        let delay = (self.stride as f64 * FIXED_DT * 100.0).round().max(1.0) as u16;
+
+        for sink in self.sinks.iter_mut() {
+            match sink {
+                Sink::Png { dir, count } => {
+                    write_png(&self.raster, dir.join(format!("frame_{:06}.png", count)))?;
+                    *count += 1;
+                }
+                Sink::Gif(encoder) => {
+                    let (width, height) = gif_size(&self.raster)?;
+                    let mut pixels = self.raster.pixels().to_vec();
+                    let mut frame = gif::Frame::from_rgba_speed(width, height, &mut pixels, 10);
+                    frame.delay = delay;
+                    encoder.write_frame(&frame).map_err(to_io_error)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Size of the raster as a GIF stores it, which is at most 65535 pixels wide and high
+fn gif_size(raster: &Raster) -> io::Result<(u16, u16)> {
+    match (
+        u16::try_from(raster.width()),
+        u16::try_from(raster.height()),
+    ) {
+        (Ok(width), Ok(height)) => Ok((width, height)),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "Frames of {}x{} are too large for a GIF",
+                raster.width(),
+                raster.height()
+            ),
+        )),
+    }
+}
+
+fn write_png(raster: &Raster, path: PathBuf) -> io::Result<()> {
+    let file = BufWriter::new(File::create(path)?);
+    let mut encoder = png::Encoder::new(file, raster.width(), raster.height());
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(to_io_error)?;
+    writer
+        .write_image_data(raster.pixels())
+        .map_err(to_io_error)
+}
+
+fn to_io_error<E: std::error::Error + Send + Sync + 'static>(err: E) -> io::Error {
+    io::Error::other(err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::celestial_bodies::body_config::PlanetConfig;
+    use crate::celestial_bodies::planet::Planet;
+    use crate::simulation::settings::SimSettings;
+    use crate::simulation::world::SimRng;
+    use crate::utils::colour;
+    use rand::SeedableRng;
+    use std::env;
+
+    fn world() -> World {
+        let config = PlanetConfig::new(0.0, 800.0, 0.0, 1.0, 0.0, 1.0);
+        let planet = Planet::from_state(&config, 0, colour::WHITE, [40.0, 50.0], [1.0, 0.0], 1.0);
+        World::new(
+            vec![planet],
+            [50.0, 50.0],
+            SimSettings::new(1.0),
+            SimRng::seed_from_u64(0),
+        )
+    }
+
+    fn export(stride: u64) -> FrameExport {
+        let encoding = ColourEncoding::Group;
+        FrameExport::new(
+            16,
+            16,
+            [100.0, 100.0],
+            stride,
+            encoding,
+            FieldOverlay::new(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn frames_are_written_every_stride_steps() {
+        let dir = env::temp_dir().join(format!("orbits_frames_{}", std::process::id()));
+        let gif_path = dir.join("run.gif");
+        let mut export = export(3);
+        export.add_png_sequence(dir.clone()).unwrap();
+        export.add_gif(gif_path.clone()).unwrap();
+        let mut world = world();
+        for _ in 0..10 {
+            world.advance();
+            export.observe(&world).unwrap();
+        }
+        // Finishes the GIF
+        drop(export);
+
+        let mut pngs: Vec<_> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .filter(|name| name.ends_with(".png"))
+            .collect();
+        pngs.sort();
+        assert_eq!(
+            pngs,
+            ["frame_000000.png", "frame_000001.png", "frame_000002.png"]
+        );
+
+        let mut decoder = gif::DecodeOptions::new()
+            .read_info(File::open(&gif_path).unwrap())
+            .unwrap();
+        let mut delays = Vec::new();
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            delays.push(frame.delay);
+        }
+        let delay = (3.0 * FIXED_DT * 100.0).round() as u16;
+        assert_eq!(delays, [delay; 3]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
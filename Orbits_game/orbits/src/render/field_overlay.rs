@@ -0,0 +1,197 @@
+use super::renderer::Renderer;
+use crate::simulation::field::{FieldGrid, FieldQuantity};
+use crate::simulation::world::World;
+use crate::utils::array_logic::{Length, Normalise};
+use crate::utils::colour::{self, Colour, Gradient};
+
+const ARROW_SPACING: f64 = 48.0;
+const HEAT_SPACING: f64 = 16.0;
+const HEAT_ALPHA: f32 = 0.55;
+const ARROW_ALPHA: f32 = 0.85;
+const CONTOUR: Colour = [1.0, 1.0, 1.0, 0.35];
+const CONTOUR_LEVELS: usize = 10;
+
+/// Toggleable overlays showing the gravitational field of all bodies,
+/// a grid of force arrows and a heat map of the potential with contours.
+/// The heat map is of Newtonian gravity only, the arrows also include
+/// the 1PN term, see `World::acceleration_at` and `World::potential_at`
+#[derive(Debug, Clone, Copy)]
+pub struct FieldOverlay {
+    pub arrows: bool,
+    pub potential: bool,
+}
+
+impl FieldOverlay {
+    pub fn new() -> FieldOverlay {
+        FieldOverlay {
+            arrows: false,
+            potential: false,
+        }
+    }
+
+    /// Draw the enabled overlays over the whole world, call this
+    /// before the bodies are drawn so they stay on top
+    pub fn draw<R: Renderer>(&self, world: &World, renderer: &mut R) {
+        let size = [world.centre[0] * 2.0, world.centre[1] * 2.0];
+        if self.potential {
+            let quantity = FieldQuantity::Potential;
+            let grid = FieldGrid::sample(world, quantity, [0.0, 0.0], size, HEAT_SPACING);
+            draw_potential(&grid, renderer);
+        }
+        if self.arrows {
+            let offset = ARROW_SPACING / 2.0;
+            let area = [size[0] - offset, size[1] - offset];
+            let quantity = FieldQuantity::Acceleration;
+            let grid = FieldGrid::sample(world, quantity, [offset, offset], area, ARROW_SPACING);
+            draw_arrows(&grid, renderer);
+        }
+    }
+}
+
+/// Depth of the potential well on a log scale, the potential
+/// diverges near bodies so a linear scale would be a single spot
+fn depth(potential: f64) -> f64 {
+    (-potential).max(1e-12).log10()
+}
+
+/// Lowest and highest of the finite values
+fn finite_range<I: Iterator<Item = f64>>(values: I) -> Option<(f64, f64)> {
+    values
+        .filter(|value| value.is_finite())
+        .fold(None, |range, value| match range {
+            None => Some((value, value)),
+            Some((low, high)) => Some((low.min(value), high.max(value))),
+        })
+}
+
+fn normalise(value: f64, (low, high): (f64, f64)) -> f64 {
+    if high > low {
+        (value - low) / (high - low)
+    } else {
+        0.5
+    }
+}
+
+/// Fill every grid cell by its mean depth and trace contours
+/// of equal potential through the cells (marching squares)
+fn draw_potential<R: Renderer>(grid: &FieldGrid, renderer: &mut R) {
+    let depths: Vec<f64> = grid
+        .potential
+        .iter()
+        .map(|&potential| depth(potential))
+        .collect();
+    let range = match finite_range(depths.iter().copied()) {
+        Some(range) => range,
+        None => return,
+    };
+    let levels: Vec<f64> = (1..=CONTOUR_LEVELS)
+        .map(|i| range.0 + (range.1 - range.0) * i as f64 / (CONTOUR_LEVELS + 1) as f64)
+        .collect();
+
+    for y in 0..grid.nodes[1].saturating_sub(1) {
+        for x in 0..grid.nodes[0].saturating_sub(1) {
+            // Corners in order around the cell
+            let corners = [(x, y), (x + 1, y), (x + 1, y + 1), (x, y + 1)];
+            let values = corners.map(|(cx, cy)| depths[grid.index(cx, cy)]);
+            if values.iter().any(|value| !value.is_finite()) {
+                continue;
+            }
+            let mean = values.iter().sum::<f64>() / 4.0;
+            let fill =
+                colour::with_alpha(Gradient::Magma.sample(normalise(mean, range)), HEAT_ALPHA);
+            let pos = grid.position(x, y);
+            renderer.rectangle(fill, [pos[0], pos[1], grid.spacing, grid.spacing]);
+
+            let points = corners.map(|(cx, cy)| grid.position(cx, cy));
+            for &level in levels.iter() {
+                draw_contour_cell(points, values, level, renderer);
+            }
+        }
+    }
+}
+
+/// Draw the part of the contour at `level` that crosses one cell
+fn draw_contour_cell<R: Renderer>(
+    points: [[f64; 2]; 4],
+    values: [f64; 4],
+    level: f64,
+    renderer: &mut R,
+) {
+    let mut crossings: Vec<[f64; 2]> = Vec::with_capacity(4);
+    for edge in 0..4 {
+        let (a, b) = (edge, (edge + 1) % 4);
+        if (values[a] < level) == (values[b] < level) {
+            continue;
+        }
+        let t = (level - values[a]) / (values[b] - values[a]);
+        crossings.push([
+            points[a][0] + (points[b][0] - points[a][0]) * t,
+            points[a][1] + (points[b][1] - points[a][1]) * t,
+        ]);
+    }
+    // Two crossings make one segment, a saddle has four and two segments
+    for pair in crossings.chunks_exact(2) {
+        renderer.line(
+            CONTOUR,
+            0.5,
+            [pair[0][0], pair[0][1], pair[1][0], pair[1][1]],
+        );
+    }
+}
+
+/// Draw an arrow on every node pointing along the acceleration,
+/// length and colour grow with the log of its magnitude
+fn draw_arrows<R: Renderer>(grid: &FieldGrid, renderer: &mut R) {
+    let magnitudes: Vec<f64> = grid
+        .acceleration
+        .iter()
+        .map(|acc| acc.get_length().max(1e-12).log10())
+        .collect();
+    let range = match finite_range(magnitudes.iter().copied()) {
+        Some(range) => range,
+        None => return,
+    };
+
+    for y in 0..grid.nodes[1] {
+        for x in 0..grid.nodes[0] {
+            let index = grid.index(x, y);
+            let acc = grid.acceleration[index];
+            if !magnitudes[index].is_finite() || acc.get_length() == 0.0 {
+                continue;
+            }
+            let t = normalise(magnitudes[index], range);
+            let colour = colour::with_alpha(Gradient::Viridis.sample(t), ARROW_ALPHA);
+            let length = grid.spacing * (0.25 + 0.6 * t);
+            draw_arrow(
+                grid.position(x, y),
+                acc.normalise(),
+                length,
+                colour,
+                renderer,
+            );
+        }
+    }
+}
+
+/// Arrow starting at `from` along the unit vector `dir`
+fn draw_arrow<R: Renderer>(
+    from: [f64; 2],
+    dir: [f64; 2],
+    length: f64,
+    colour: Colour,
+    renderer: &mut R,
+) {
+    let tip = [from[0] + dir[0] * length, from[1] + dir[1] * length];
+    renderer.line(colour, 0.75, [from[0], from[1], tip[0], tip[1]]);
+    // Two barbs at 30 degrees either side of the shaft
+    let barb = length * 0.3;
+    let (sin, cos) = (0.5, 0.75_f64.sqrt());
+    for side in [-1.0, 1.0] {
+        let back = [
+            -(dir[0] * cos - side * dir[1] * sin),
+            -(side * dir[0] * sin + dir[1] * cos),
+        ];
+        let end = [tip[0] + back[0] * barb, tip[1] + back[1] * barb];
+        renderer.line(colour, 0.75, [tip[0], tip[1], end[0], end[1]]);
+    }
+}
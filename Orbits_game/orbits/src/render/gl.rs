@@ -0,0 +1,59 @@
+use super::renderer::Renderer;
+use crate::utils::colour::Colour;
+use graphics::Transformed;
+use opengl_graphics::{GlGraphics, GlyphCache, TextureSettings};
+
+const FONT: &[u8] = include_bytes!("../../assets/DejaVuSansMono.ttf");
+
+/// Load the font that is bundled with the binary
+pub fn load_font() -> GlyphCache<'static> {
+    GlyphCache::from_bytes(FONT, (), TextureSettings::new()).expect("Could not load font")
+}
+
+/// Renderer that draws into the OpenGL window
+pub struct GlRenderer<'a, 'f> {
+    c: graphics::Context,
+    g: &'a mut GlGraphics,
+    glyphs: &'a mut GlyphCache<'f>,
+}
+
+impl<'a, 'f> GlRenderer<'a, 'f> {
+    pub fn new(
+        c: graphics::Context,
+        g: &'a mut GlGraphics,
+        glyphs: &'a mut GlyphCache<'f>,
+    ) -> Self {
+        GlRenderer { c, g, glyphs }
+    }
+}
+
+impl<'a, 'f> Renderer for GlRenderer<'a, 'f> {
+    fn clear(&mut self, colour: Colour) {
+        graphics::clear(colour, self.g);
+    }
+
+    fn rectangle(&mut self, colour: Colour, rect: [f64; 4]) {
+        graphics::rectangle(colour, rect, self.c.transform, self.g);
+    }
+
+    fn circle(&mut self, colour: Colour, centre: [f64; 2], radius: f64) {
+        let rect = [
+            centre[0] - radius,
+            centre[1] - radius,
+            2.0 * radius,
+            2.0 * radius,
+        ];
+        graphics::ellipse(colour, rect, self.c.transform, self.g);
+    }
+
+    fn line(&mut self, colour: Colour, radius: f64, line: [f64; 4]) {
+        graphics::line(colour, radius, line, self.c.transform, self.g);
+    }
+
+    fn text(&mut self, colour: Colour, size: u32, pos: [f64; 2], text: &str) {
+        let transform = self.c.transform.trans(pos[0], pos[1]);
+        graphics::Text::new_color(colour, size)
+            .draw(text, self.glyphs, &self.c.draw_state, transform, self.g)
+            .expect("Could not draw text");
+    }
+}
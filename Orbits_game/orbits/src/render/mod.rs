@@ -0,0 +1,8 @@
+pub mod draw_list;
+pub mod encoding;
+pub mod export;
+pub mod field_overlay;
+pub mod gl;
+pub mod raster;
+pub mod renderer;
+pub mod svg;
@@ -0,0 +1,203 @@
+use super::renderer::Renderer;
+use crate::utils::colour::{self, Colour};
+use std::io;
+
+/// Software (CPU) rasteriser that draws into an RGBA pixel
+/// buffer, so that frames can be rendered without a window.
+/// World coordinates are scaled onto the image
+pub struct Raster {
+    width: u32,
+    height: u32,
+    /// Pixels per world unit along x and y
+    scale: [f64; 2],
+    pixels: Vec<u8>,
+}
+
+impl Raster {
+    /// Create an image of the given resolution, onto which a world area
+    /// of size `view` is mapped. Fails when the image does not fit in memory
+    pub fn new(width: u32, height: u32, view: [f64; 2]) -> io::Result<Raster> {
+        let size = (width as usize)
+            .checked_mul(height as usize)
+            .and_then(|pixels| pixels.checked_mul(4));
+        let mut pixels = Vec::new();
+        match size {
+            Some(size) if pixels.try_reserve_exact(size).is_ok() => pixels.resize(size, 0),
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::OutOfMemory,
+                    format!("Frames of {}x{} are too large to render", width, height),
+                ))
+            }
+        }
+        Ok(Raster {
+            width,
+            height,
+            scale: [width as f64 / view[0], height as f64 / view[1]],
+            pixels,
+        })
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// The image as RGBA bytes, row by row
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    /// Blend a colour over the pixel at (x, y), ignoring
+    /// pixels outside of the image
+    fn blend(&mut self, x: i64, y: i64, colour: Colour) {
+        if x < 0 || y < 0 || x >= self.width as i64 || y >= self.height as i64 {
+            return;
+        }
+        let index = (y as usize * self.width as usize + x as usize) * 4;
+        let mut under = [0.0; 4];
+        for (channel, byte) in under.iter_mut().zip(&self.pixels[index..index + 4]) {
+            *channel = *byte as f32 / 255.0;
+        }
+        let mixed = to_bytes(colour::blend(colour, under));
+        self.pixels[index..index + 4].copy_from_slice(&mixed);
+    }
+}
+
+fn to_bytes(colour: Colour) -> [u8; 4] {
+    let mut bytes = [0; 4];
+    for (byte, channel) in bytes.iter_mut().zip(colour.iter()) {
+        *byte = (channel.clamp(0.0, 1.0) * 255.0).round() as u8;
+    }
+    bytes
+}
+
+impl Renderer for Raster {
+    fn clear(&mut self, colour: Colour) {
+        let rgba = to_bytes(colour);
+        for pixel in self.pixels.chunks_exact_mut(4) {
+            pixel.copy_from_slice(&rgba);
+        }
+    }
+
+    /// At least one pixel is drawn so that tiny bodies stay visible,
+    /// edges are rounded so adjacent rectangles do not overlap
+    fn rectangle(&mut self, colour: Colour, rect: [f64; 4]) {
+        let x0 = (rect[0] * self.scale[0]).round() as i64;
+        let y0 = (rect[1] * self.scale[1]).round() as i64;
+        let x1 = ((rect[0] + rect[2]) * self.scale[0])
+            .round()
+            .max(x0 as f64 + 1.0) as i64;
+        let y1 = ((rect[1] + rect[3]) * self.scale[1])
+            .round()
+            .max(y0 as f64 + 1.0) as i64;
+        for y in y0..y1 {
+            for x in x0..x1 {
+                self.blend(x, y, colour);
+            }
+        }
+    }
+
+    /// Fills the pixels with their centre inside the circle,
+    /// again at least the pixel under the centre
+    fn circle(&mut self, colour: Colour, centre: [f64; 2], radius: f64) {
+        let (cx, cy) = (centre[0] * self.scale[0], centre[1] * self.scale[1]);
+        let (rx, ry) = (radius * self.scale[0], radius * self.scale[1]);
+        if rx < 0.5 || ry < 0.5 {
+            self.blend(cx.floor() as i64, cy.floor() as i64, colour);
+            return;
+        }
+        for y in (cy - ry).floor() as i64..=(cy + ry).ceil() as i64 {
+            for x in (cx - rx).floor() as i64..=(cx + rx).ceil() as i64 {
+                let dx = (x as f64 + 0.5 - cx) / rx;
+                let dy = (y as f64 + 0.5 - cy) / ry;
+                if dx * dx + dy * dy <= 1.0 {
+                    self.blend(x, y, colour);
+                }
+            }
+        }
+    }
+
+    fn line(&mut self, colour: Colour, radius: f64, line: [f64; 4]) {
+        let from = [line[0] * self.scale[0], line[1] * self.scale[1]];
+        let to = [line[2] * self.scale[0], line[3] * self.scale[1]];
+        let length = ((to[0] - from[0]).powi(2) + (to[1] - from[1]).powi(2)).sqrt();
+        let half_width = (radius * self.scale[0]).max(0.5);
+        // Stamp a square every half pixel along the line
+        let samples = (length * 2.0).ceil().max(1.0) as usize;
+        let mut last: Option<(i64, i64, i64, i64)> = None;
+        for i in 0..=samples {
+            let t = i as f64 / samples as f64;
+            let x = from[0] + (to[0] - from[0]) * t;
+            let y = from[1] + (to[1] - from[1]) * t;
+            let stamp = (
+                (x - half_width).floor() as i64,
+                (y - half_width).floor() as i64,
+                (x + half_width).ceil() as i64,
+                (y + half_width).ceil() as i64,
+            );
+            // Don't blend the same pixels twice for translucent colours
+            if last == Some(stamp) {
+                continue;
+            }
+            last = Some(stamp);
+            for py in stamp.1..stamp.3 {
+                for px in stamp.0..stamp.2 {
+                    self.blend(px, py, colour);
+                }
+            }
+        }
+    }
+
+    /// Text is not rasterised, offscreen frames only show the bodies
+    fn text(&mut self, _colour: Colour, _size: u32, _pos: [f64; 2], _text: &str) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pixel(raster: &Raster, x: usize, y: usize) -> [u8; 4] {
+        let index = (y * raster.width() as usize + x) * 4;
+        raster.pixels()[index..index + 4].try_into().unwrap()
+    }
+
+    #[test]
+    fn circle_fills_the_pixels_inside_it() {
+        // Two pixels per unit of the world
+        let mut raster = Raster::new(20, 20, [10.0, 10.0]).unwrap();
+        raster.clear(colour::BLACK);
+        raster.circle(colour::RED, [5.0, 5.0], 2.0);
+        let red = [255, 0, 0, 255];
+        let black = [0, 0, 0, 255];
+        for (x, y) in [(10, 10), (9, 9), (6, 10), (13, 10), (10, 6), (10, 13)] {
+            assert_eq!(pixel(&raster, x, y), red, "pixel {},{}", x, y);
+        }
+        for (x, y) in [(5, 10), (14, 10), (10, 5), (10, 14), (6, 6), (0, 0)] {
+            assert_eq!(pixel(&raster, x, y), black, "pixel {},{}", x, y);
+        }
+    }
+
+    #[test]
+    fn line_covers_its_width() {
+        let mut raster = Raster::new(10, 10, [10.0, 10.0]).unwrap();
+        raster.clear(colour::BLACK);
+        raster.line(colour::WHITE, 0.5, [2.0, 5.0, 7.0, 5.0]);
+        for x in 2..7 {
+            assert_eq!(pixel(&raster, x, 4), [255; 4]);
+            assert_eq!(pixel(&raster, x, 5), [255; 4]);
+            assert_eq!(pixel(&raster, x, 3), [0, 0, 0, 255]);
+            assert_eq!(pixel(&raster, x, 6), [0, 0, 0, 255]);
+        }
+        assert_eq!(pixel(&raster, 0, 5), [0, 0, 0, 255]);
+        assert_eq!(pixel(&raster, 9, 5), [0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn oversized_image_is_refused() {
+        assert!(Raster::new(u32::MAX, u32::MAX, [1.0, 1.0]).is_err());
+    }
+}
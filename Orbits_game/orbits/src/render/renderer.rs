@@ -0,0 +1,45 @@
+use super::encoding::ColourEncoding;
+use super::field_overlay::FieldOverlay;
+use crate::simulation::world::World;
+use crate::utils::colour::{self, Colour};
+
+const BACKGROUND: Colour = colour::BLACK;
+
+/// A backend that draws primitives, all positions are in
+/// world coordinates. Bodies and overlays only emit these
+/// primitives, so any backend can show them
+pub trait Renderer {
+    fn clear(&mut self, colour: Colour);
+    /// Fill a rectangle [x, y, w, h]
+    fn rectangle(&mut self, colour: Colour, rect: [f64; 4]);
+    fn circle(&mut self, colour: Colour, centre: [f64; 2], radius: f64);
+    /// Draw a line [x1, y1, x2, y2] with the given radius
+    fn line(&mut self, colour: Colour, radius: f64, line: [f64; 4]);
+    /// Draw text with its baseline starting at `pos`
+    fn text(&mut self, colour: Colour, size: u32, pos: [f64; 2], text: &str);
+}
+
+/// Draw every body in the world on a cleared background with
+/// the field overlays beneath, coloured according to the encoding
+pub fn draw_world<R: Renderer>(
+    world: &World,
+    encoding: ColourEncoding,
+    field: &FieldOverlay,
+    renderer: &mut R,
+) {
+    draw_background(renderer);
+    field.draw(world, renderer);
+    draw_bodies(world, encoding, renderer);
+}
+
+fn draw_background<R: Renderer>(renderer: &mut R) {
+    renderer.clear(BACKGROUND);
+}
+
+/// Draw every body in the world, coloured according to the encoding
+fn draw_bodies<R: Renderer>(world: &World, encoding: ColourEncoding, renderer: &mut R) {
+    let colours = encoding.colours(world);
+    for (planet, colour) in world.planets.iter().zip(colours) {
+        planet.draw(renderer, colour);
+    }
+}
@@ -0,0 +1,128 @@
+use super::renderer::Renderer;
+use crate::utils::colour::{self, Colour};
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Renderer that builds an SVG document of a single frame
+pub struct SvgRenderer {
+    view: [f64; 2],
+    body: String,
+}
+
+impl SvgRenderer {
+    /// An SVG showing a world area of size `view`
+    pub fn new(view: [f64; 2]) -> SvgRenderer {
+        SvgRenderer {
+            view,
+            body: String::new(),
+        }
+    }
+
+    /// The complete SVG document
+    pub fn finish(&self) -> String {
+        format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">\n{body}</svg>\n",
+            w = self.view[0],
+            h = self.view[1],
+            body = self.body
+        )
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.finish())
+    }
+}
+
+/// An SVG colour and opacity, e.g. `#ff0000` and `1`
+fn svg_colour(colour: Colour) -> (String, f32) {
+    (
+        colour::to_hex(colour::with_alpha(colour, 1.0)),
+        colour[3].clamp(0.0, 1.0),
+    )
+}
+
+/// Escape the characters that have a meaning in XML
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+impl Renderer for SvgRenderer {
+    fn clear(&mut self, colour: Colour) {
+        let (fill, opacity) = svg_colour(colour);
+        self.body.clear();
+        writeln!(
+            self.body,
+            "<rect width=\"100%\" height=\"100%\" fill=\"{}\" fill-opacity=\"{}\"/>",
+            fill, opacity
+        )
+        .unwrap();
+    }
+
+    fn rectangle(&mut self, colour: Colour, rect: [f64; 4]) {
+        let (fill, opacity) = svg_colour(colour);
+        writeln!(
+            self.body,
+            "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\" fill-opacity=\"{}\"/>",
+            rect[0], rect[1], rect[2], rect[3], fill, opacity
+        )
+        .unwrap();
+    }
+
+    fn circle(&mut self, colour: Colour, centre: [f64; 2], radius: f64) {
+        let (fill, opacity) = svg_colour(colour);
+        writeln!(
+            self.body,
+            "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"{}\" fill-opacity=\"{}\"/>",
+            centre[0], centre[1], radius, fill, opacity
+        )
+        .unwrap();
+    }
+
+    fn line(&mut self, colour: Colour, radius: f64, line: [f64; 4]) {
+        let (stroke, opacity) = svg_colour(colour);
+        writeln!(
+            self.body,
+            "<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"{}\" stroke-opacity=\"{}\" stroke-width=\"{}\"/>",
+            line[0], line[1], line[2], line[3], stroke, opacity, 2.0 * radius
+        )
+        .unwrap();
+    }
+
+    fn text(&mut self, colour: Colour, size: u32, pos: [f64; 2], text: &str) {
+        let (fill, opacity) = svg_colour(colour);
+        writeln!(
+            self.body,
+            "<text x=\"{}\" y=\"{}\" font-family=\"DejaVu Sans Mono, monospace\" font-size=\"{}\" fill=\"{}\" fill-opacity=\"{}\" xml:space=\"preserve\">{}</text>",
+            pos[0], pos[1], size, fill, opacity, escape(text)
+        )
+        .unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn primitives_become_svg_elements() {
+        let mut svg = SvgRenderer::new([200.0, 100.0]);
+        svg.circle(colour::RED, [10.0, 20.0], 5.0);
+        svg.clear([0.0, 0.0, 0.0, 0.5]);
+        svg.circle(colour::RED, [10.0, 20.0], 5.0);
+        svg.line(colour::WHITE, 0.5, [0.0, 1.0, 2.0, 3.0]);
+        svg.text(colour::WHITE, 12, [4.0, 8.0], "a<b & c");
+        assert_eq!(
+            svg.finish(),
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"200\" height=\"100\" viewBox=\"0 0 200 100\">\n\
+             <rect width=\"100%\" height=\"100%\" fill=\"#000000\" fill-opacity=\"0.5\"/>\n\
+             <circle cx=\"10\" cy=\"20\" r=\"5\" fill=\"#ff0000\" fill-opacity=\"1\"/>\n\
+             <line x1=\"0\" y1=\"1\" x2=\"2\" y2=\"3\" stroke=\"#ffffff\" stroke-opacity=\"1\" stroke-width=\"1\"/>\n\
+             <text x=\"4\" y=\"8\" font-family=\"DejaVu Sans Mono, monospace\" font-size=\"12\" fill=\"#ffffff\" fill-opacity=\"1\" xml:space=\"preserve\">a&lt;b &amp; c</text>\n\
+             </svg>\n"
+        );
+    }
+}
@@ -0,0 +1,215 @@
+use crate::celestial_bodies::planet::Planet;
+use crate::simulation::world::{World, FIXED_DT};
+use serde::Serialize;
+use std::io::{self, Read, Write};
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::sync::mpsc::{self, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tungstenite::handshake::server::{ErrorResponse, Request, Response};
+use tungstenite::http::StatusCode;
+use tungstenite::Message;
+
+const VIEWER: &str = include_str!("../assets/viewer.html");
+// Steps between two frames sent, 60 frames a second in real time
+const FRAME_STEPS: u64 = 2;
+// Frames waiting to be sent to a viewer before newer ones are
+// dropped for it, so a slow viewer does not hold up the others
+const CLIENT_BACKLOG: usize = 4;
+// Bytes of a request looked at to tell the page from a WebSocket
+const REQUEST_HEAD: usize = 4096;
+
+/// The state of the world sent to viewers, the bodies
+/// are sent as the simulation keeps them
+#[derive(Serialize)]
+struct Frame<'a> {
+    time: f64,
+    steps: u64,
+    centre: [f64; 2],
+    planets: &'a [Planet],
+}
+
+type Clients = Arc<Mutex<Vec<SyncSender<Arc<String>>>>>;
+
+/// Serves the viewer page and streams the world to every browser
+/// connected to it. Only listens on the loopback interface and only
+/// streams to its own page, so the run can not be watched from other
+/// machines or by other pages open in the browser
+pub struct Server {
+    clients: Clients,
+    /// When the first step was seen and its number, to run in real time
+    pace: Option<(Instant, u64)>,
+}
+
+impl Server {
+    /// Listen on the port, connections are answered on threads of their own
+    pub fn start(port: u16) -> io::Result<Server> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))?;
+        let clients: Clients = Arc::new(Mutex::new(Vec::new()));
+        let accepted = Arc::clone(&clients);
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let clients = Arc::clone(&accepted);
+                thread::spawn(move || handle(stream, port, &clients));
+            }
+        });
+        Ok(Server {
+            clients,
+            pace: None,
+        })
+    }
+
+    /// Send the world after a step when a frame is due, waiting
+    /// first so the simulation runs in real time while it is watched
+    pub fn observe(&mut self, world: &World) {
+        let (started, first_step) = *self.pace.get_or_insert((Instant::now(), world.steps));
+        let due = started
+            + Duration::from_secs_f64(world.steps.saturating_sub(first_step) as f64 * FIXED_DT);
+        if let Some(wait) = due.checked_duration_since(Instant::now()) {
+            thread::sleep(wait);
+        }
+        if !world.steps.is_multiple_of(FRAME_STEPS) {
+            return;
+        }
+
+        let frame = Frame {
+            time: world.time,
+            steps: world.steps,
+            centre: world.centre,
+            planets: &world.planets,
+        };
+        let text = match serde_json::to_string(&frame) {
+            Ok(text) => Arc::new(text),
+            Err(err) => {
+                eprintln!("Could not encode frame: {}", err);
+                return;
+            }
+        };
+        // Viewers that went away are dropped, slow ones skip the frame
+        self.clients
+            .lock()
+            .unwrap()
+            .retain(|client| match client.try_send(Arc::clone(&text)) {
+                Ok(()) | Err(TrySendError::Full(_)) => true,
+                Err(TrySendError::Disconnected(_)) => false,
+            });
+    }
+}
+
+/// Answer one connection, with the viewer page for a plain request or by
+/// streaming frames until the browser leaves when it asks for a WebSocket
+// The refusal's size is tungstenite's, its handshake callback has to return it
+#[allow(clippy::result_large_err)]
+fn handle(stream: TcpStream, port: u16, clients: &Clients) {
+    let mut head = [0; REQUEST_HEAD];
+    let read = match stream.peek(&mut head) {
+        Ok(read) => read,
+        Err(_) => return,
+    };
+    let head = String::from_utf8_lossy(&head[..read]).to_ascii_lowercase();
+    if !head.contains("upgrade: websocket") {
+        if let Err(err) = serve_page(stream) {
+            eprintln!("Could not serve the viewer: {}", err);
+        }
+        return;
+    }
+
+    let mut socket = match tungstenite::accept_hdr(stream, |request: &Request, response| {
+        same_origin(request, response, port)
+    }) {
+        Ok(socket) => socket,
+        Err(err) => {
+            eprintln!("Could not open WebSocket: {}", err);
+            return;
+        }
+    };
+    let (sender, frames) = mpsc::sync_channel(CLIENT_BACKLOG);
+    clients.lock().unwrap().push(sender);
+    for frame in frames {
+        if socket.send(Message::Text(frame.to_string())).is_err() {
+            return;
+        }
+    }
+}
+
+/// Accept a WebSocket only when it is opened by the viewer served here.
+/// Browsers send the origin of the page along, which other pages can't fake
+#[allow(clippy::result_large_err)]
+fn same_origin(
+    request: &Request,
+    response: Response,
+    port: u16,
+) -> Result<Response, ErrorResponse> {
+    let origin = request
+        .headers()
+        .get("origin")
+        .and_then(|origin| origin.to_str().ok());
+    let allowed = [
+        format!("http://127.0.0.1:{}", port),
+        format!("http://localhost:{}", port),
+    ];
+    match origin {
+        Some(origin) if allowed.iter().any(|allowed| allowed == origin) => Ok(response),
+        _ => {
+            let mut refusal = ErrorResponse::new(Some("Forbidden origin".to_string()));
+            *refusal.status_mut() = StatusCode::FORBIDDEN;
+            Err(refusal)
+        }
+    }
+}
+
+/// Send the viewer for the root path and a 404 for anything else
+fn serve_page(mut stream: TcpStream) -> io::Result<()> {
+    let mut request = [0; REQUEST_HEAD];
+    let read = stream.read(&mut request)?;
+    let request = String::from_utf8_lossy(&request[..read]);
+    let (status, body) = if request.starts_with("GET / ") || request.starts_with("GET /index.html ")
+    {
+        ("200 OK", VIEWER)
+    } else {
+        ("404 Not Found", "Not found")
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Status of the answer to a WebSocket request sent with the origin
+    fn answer(origin: Option<&str>, port: u16) -> StatusCode {
+        let mut request = Request::builder().uri("/");
+        if let Some(origin) = origin {
+            request = request.header("Origin", origin);
+        }
+        match same_origin(&request.body(()).unwrap(), Response::new(()), port) {
+            Ok(response) => response.status(),
+            Err(refusal) => refusal.status(),
+        }
+    }
+
+    #[test]
+    fn only_the_served_viewer_may_connect() {
+        assert_eq!(answer(Some("http://127.0.0.1:8080"), 8080), StatusCode::OK);
+        assert_eq!(answer(Some("http://localhost:8080"), 8080), StatusCode::OK);
+
+        for origin in [
+            None,
+            Some("http://127.0.0.1:9090"),
+            Some("http://localhost"),
+            Some("https://localhost:8080"),
+            Some("http://example.com"),
+            Some("null"),
+        ] {
+            assert_eq!(answer(origin, 8080), StatusCode::FORBIDDEN, "{:?}", origin);
+        }
+    }
+}
@@ -0,0 +1,54 @@
+use super::settings::Integrator;
+use super::world::World;
+use crate::celestial_bodies::planet::Planet;
+use serde::{Deserialize, Serialize};
+
+/// A change made to the world from outside the simulation,
+/// e.g. by the user. Every such change goes through an
+/// action so that sessions can be recorded and replayed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Action {
+    Spawn(Planet),
+    Remove { index: usize },
+    SetMass { index: usize, mass: f64 },
+    SetVelocity { index: usize, velocity: [f64; 2] },
+    SetTimeScale(f64),
+    SetIntegrator(Integrator),
+    SetSpeedOfLight(Option<f64>),
+    SetTidalDisruption(bool),
+    SetCollisions(bool),
+}
+
+impl Action {
+    /// Apply the action to the world, actions aimed
+    /// at bodies that no longer exist are ignored
+    pub fn apply(&self, world: &mut World) {
+        match self {
+            Action::Spawn(planet) => world.spawn(planet.clone()),
+            Action::Remove { index } => {
+                if *index < world.planets.len() {
+                    world.remove(*index);
+                }
+            }
+            Action::SetMass { index, mass } => {
+                if let Some(planet) = world.planets.get_mut(*index) {
+                    planet.set_mass(*mass);
+                }
+            }
+            Action::SetVelocity { index, velocity } => {
+                if let Some(planet) = world.planets.get_mut(*index) {
+                    planet.set_velocity(*velocity);
+                }
+            }
+            Action::SetTimeScale(time_scale) => world.settings.time_scale = *time_scale,
+            Action::SetIntegrator(integrator) => world.settings.integrator = *integrator,
+            Action::SetSpeedOfLight(speed_of_light) => {
+                world.settings.speed_of_light = *speed_of_light
+            }
+            Action::SetTidalDisruption(enabled) => world.settings.tidal_disruption = *enabled,
+            Action::SetCollisions(enabled) => world.settings.collisions = *enabled,
+        }
+        // Bodies changed by hand make the old energy meaningless
+        world.rebase_energy();
+    }
+}
@@ -0,0 +1,418 @@
+use super::units::{Dimension, Unit, UnitSystem};
+use crate::celestial_bodies::body_config::PlanetConfig;
+use crate::celestial_bodies::planet::Planet;
+use crate::utils::colour;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+const COLUMNS: [&str; 9] = ["name", "mass", "x", "y", "z", "vx", "vy", "vz", "epoch"];
+
+/// Initial state of one body, in SI units
+#[derive(Debug, Clone, PartialEq)]
+pub struct EphemerisBody {
+    pub name: String,
+    pub mass: f64,
+    pub position: [f64; 3],
+    pub velocity: [f64; 3],
+    /// Julian date the state is given at
+    pub epoch: f64,
+}
+
+/// How the 3D state vectors are flattened onto the plane
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Projection {
+    /// Drop z, i.e. project onto the reference plane of the file (e.g. the ecliptic)
+    ReferencePlane,
+    /// Project onto the plane perpendicular to the total angular momentum,
+    /// which keeps as much of the orbital motion as possible
+    Invariable,
+}
+
+/// State vectors of bodies read from a CSV export such as one from JPL
+/// Horizons. The first line names the columns name, mass, x, y, z, vx, vy,
+/// vz and epoch in any order, each optionally followed by a unit in
+/// brackets, e.g. `x (AU)` or `vx [km/s]`. Without one, lengths are in
+/// km, velocities in km/s and masses in kg. Lines starting with # and
+/// Horizons' $$SOE/$$EOE markers are skipped
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ephemeris {
+    pub bodies: Vec<EphemerisBody>,
+}
+
+impl Ephemeris {
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Ephemeris> {
+        let contents = fs::read_to_string(path)?;
+        Ephemeris::parse(&contents).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    pub fn parse(text: &str) -> Result<Ephemeris, String> {
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i + 1, line.trim()))
+            .filter(|(_, line)| {
+                !line.is_empty() && !line.starts_with('#') && !line.starts_with("$$")
+            });
+        let (_, header) = lines.next().ok_or("The file has no header line")?;
+        let columns = Columns::parse(header)?;
+
+        let mut bodies = Vec::new();
+        for (number, line) in lines {
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            let body = columns
+                .body(&fields)
+                .map_err(|err| format!("Line {}: {}", number, err))?;
+            bodies.push(body);
+        }
+        if bodies.is_empty() {
+            return Err("The file has no bodies".to_string());
+        }
+        Ok(Ephemeris { bodies })
+    }
+
+    /// Whether all states are given at the same epoch
+    pub fn same_epoch(&self) -> bool {
+        self.bodies
+            .windows(2)
+            .all(|pair| (pair[0].epoch - pair[1].epoch).abs() < 1e-6)
+    }
+
+    /// Planets in simulation units, projected onto the plane and moved into
+    /// the barycentric frame with the barycentre at `centre`. Ids count up
+    /// from `first_id` and every body gets its own colour
+    pub fn to_planets(
+        &self,
+        units: &UnitSystem,
+        projection: Projection,
+        planet_const: &PlanetConfig,
+        centre: [f64; 2],
+        first_id: u32,
+    ) -> Vec<Planet> {
+        let total_mass: f64 = self.bodies.iter().map(|body| body.mass).sum();
+        let mean = |state: fn(&EphemerisBody) -> [f64; 3]| {
+            let mut sum = [0.0; 3];
+            for body in self.bodies.iter() {
+                for (k, value) in state(body).iter().enumerate() {
+                    sum[k] += value * body.mass / total_mass;
+                }
+            }
+            sum
+        };
+        let barycentre = mean(|body| body.position);
+        let drift = mean(|body| body.velocity);
+
+        let basis = match projection {
+            Projection::ReferencePlane => [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0]],
+            Projection::Invariable => self.invariable_basis(barycentre, drift),
+        };
+        let project = |v: [f64; 3], origin: [f64; 3]| {
+            let v = sub(v, origin);
+            [dot(v, basis[0]), dot(v, basis[1])]
+        };
+
+        self.bodies
+            .iter()
+            .enumerate()
+            .map(|(i, body)| {
+                let pos = project(body.position, barycentre);
+                let vel = project(body.velocity, drift);
+                // Screen y points down, so flip it to keep the orbits counter-clockwise
+                let position = [
+                    centre[0] + units.to_sim(pos[0], Dimension::Length),
+                    centre[1] - units.to_sim(pos[1], Dimension::Length),
+                ];
+                let velocity = [
+                    units.to_sim(vel[0], Dimension::Velocity),
+                    -units.to_sim(vel[1], Dimension::Velocity),
+                ];
+                let mass = units.to_sim(body.mass, Dimension::Mass);
+                let id = first_id + i as u32;
+                Planet::from_state(
+                    planet_const,
+                    id,
+                    colour::for_id(id as u64),
+                    position,
+                    velocity,
+                    mass,
+                )
+            })
+            .collect()
+    }
+
+    /// Two unit vectors spanning the plane perpendicular to
+    /// the total angular momentum about the barycentre
+    fn invariable_basis(&self, barycentre: [f64; 3], drift: [f64; 3]) -> [[f64; 3]; 2] {
+        let mut momentum = [0.0; 3];
+        for body in self.bodies.iter() {
+            let l = cross(sub(body.position, barycentre), sub(body.velocity, drift));
+            for (total, component) in momentum.iter_mut().zip(l) {
+                *total += body.mass * component;
+            }
+        }
+        let normal = match normalise(momentum) {
+            Some(normal) => normal,
+            None => return [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0]],
+        };
+        // Keep the reference x axis as close as possible, unless it
+        // is (nearly) along the normal itself
+        let reference = if normal[0].abs() < 0.9 {
+            [1.0, 0.0, 0.0]
+        } else {
+            [0.0, 1.0, 0.0]
+        };
+        let along = dot(reference, normal);
+        let x = normalise([
+            reference[0] - along * normal[0],
+            reference[1] - along * normal[1],
+            reference[2] - along * normal[2],
+        ])
+        .unwrap_or([1.0, 0.0, 0.0]);
+        [x, cross(normal, x)]
+    }
+}
+
+/// Where each column is found and the unit it is in
+struct Columns {
+    index: [usize; 9],
+    length: Unit,
+    velocity: Unit,
+    mass: Unit,
+}
+
+impl Columns {
+    fn parse(header: &str) -> Result<Columns, String> {
+        let mut index = [usize::MAX; 9];
+        let (mut length, mut velocity, mut mass) =
+            (Unit::Kilometre, Unit::KilometresPerSecond, Unit::Kilogram);
+        for (i, field) in header.split(',').enumerate() {
+            let (name, unit) = split_unit(field.trim());
+            let column = match COLUMNS
+                .iter()
+                .position(|column| column.eq_ignore_ascii_case(name))
+            {
+                Some(column) => column,
+                // Other columns, e.g. Horizons' light time, are ignored
+                None => continue,
+            };
+            index[column] = i;
+            if let Some(symbol) = unit {
+                let unit = Unit::from_symbol(symbol)
+                    .ok_or_else(|| format!("Unknown unit '{}'", symbol))?;
+                let expected = match column {
+                    1 => &mut mass,
+                    2..=4 => &mut length,
+                    5..=7 => &mut velocity,
+                    _ => continue,
+                };
+                if unit.dimension() != expected.dimension() {
+                    return Err(format!("Unit '{}' does not fit column '{}'", symbol, name));
+                }
+                *expected = unit;
+            }
+        }
+        if let Some(missing) = index.iter().position(|&i| i == usize::MAX) {
+            return Err(format!("The header has no '{}' column", COLUMNS[missing]));
+        }
+        Ok(Columns {
+            index,
+            length,
+            velocity,
+            mass,
+        })
+    }
+
+    fn body(&self, fields: &[&str]) -> Result<EphemerisBody, String> {
+        let field = |column: usize| {
+            fields
+                .get(self.index[column])
+                .copied()
+                .ok_or_else(|| format!("Missing value for '{}'", COLUMNS[column]))
+        };
+        let number = |column: usize| -> Result<f64, String> {
+            let raw = field(column)?;
+            raw.parse()
+                .ok()
+                .filter(|value: &f64| value.is_finite())
+                .ok_or_else(|| format!("Invalid value '{}' for '{}'", raw, COLUMNS[column]))
+        };
+        let vector = |first: usize, unit: Unit| -> Result<[f64; 3], String> {
+            let factor = unit.si_factor();
+            Ok([
+                number(first)? * factor,
+                number(first + 1)? * factor,
+                number(first + 2)? * factor,
+            ])
+        };
+        // The barycentre is weighted by the masses, each has to count
+        let mass = number(1)?;
+        if mass <= 0.0 {
+            return Err(format!("Mass must be above 0, got {}", mass));
+        }
+        Ok(EphemerisBody {
+            name: field(0)?.to_string(),
+            mass: mass * self.mass.si_factor(),
+            position: vector(2, self.length)?,
+            velocity: vector(5, self.velocity)?,
+            epoch: number(8)?,
+        })
+    }
+}
+
+/// Split a column header such as "x (AU)" or "vx [km/s]" into its name and unit
+fn split_unit(field: &str) -> (&str, Option<&str>) {
+    match field.find(['(', '[']) {
+        Some(start) => {
+            let unit = field[start + 1..].trim_end_matches([')', ']']).trim();
+            (field[..start].trim(), Some(unit))
+        }
+        None => (field, None),
+    }
+}
+
+fn sub(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn dot(a: [f64; 3], b: [f64; 3]) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn normalise(v: [f64; 3]) -> Option<[f64; 3]> {
+    let length = dot(v, v).sqrt();
+    if length > 0.0 {
+        Some([v[0] / length, v[1] / length, v[2] / length])
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::celestial_bodies::body_config::CelestialBody;
+    use crate::simulation::units::{UnitStyle, ASTRONOMICAL_UNIT, DAY};
+
+    #[test]
+    fn parse_with_units_in_any_order() {
+        let text =
+            "epoch, name, mass (Msun), x (AU), y (AU), z (AU), vx [AU/d], vy [AU/d], vz [AU/d]\n\
+                    2451545.0, Sun, 1, 0, 0, 0, 0, 0, 0\n\
+                    2451545.0, Earth, 3e-6, 1, 0, 0.5, 0, 0.0172, 0\n";
+        let ephemeris = Ephemeris::parse(text).unwrap();
+        let earth = &ephemeris.bodies[1];
+        assert_eq!(earth.name, "Earth");
+        assert_eq!(
+            earth.position,
+            [ASTRONOMICAL_UNIT, 0.0, 0.5 * ASTRONOMICAL_UNIT]
+        );
+        assert_eq!(earth.velocity[1], 0.0172 * ASTRONOMICAL_UNIT / DAY);
+        assert_eq!(earth.epoch, 2451545.0);
+        assert!(ephemeris.same_epoch());
+    }
+
+    #[test]
+    fn km_and_kg_without_units() {
+        let text = "name,mass,x,y,z,vx,vy,vz,epoch\nMoon,7.3e22,384400,0,0,0,1.02,0,1\n";
+        let moon = &Ephemeris::parse(text).unwrap().bodies[0];
+        assert_eq!(moon.mass, 7.3e22);
+        assert_eq!(moon.position[0], 384_400_000.0);
+        assert_eq!(moon.velocity[1], 1020.0);
+    }
+
+    #[test]
+    fn horizons_markers_comments_and_extra_columns_are_skipped() {
+        let text = "# Exported from Horizons\n\
+                    name,mass,x,y,z,vx,vy,vz,epoch,LT\n\
+                    $$SOE\n\
+                    Sun,2e30,0,0,0,0,0,0,5,0.1\n\
+                    \n\
+                    Mars,6.4e23,2.3e8,0,0,0,24,0,6,0.2\n\
+                    $$EOE\n";
+        let ephemeris = Ephemeris::parse(text).unwrap();
+        assert_eq!(ephemeris.bodies.len(), 2);
+        assert!(!ephemeris.same_epoch());
+    }
+
+    #[test]
+    fn bad_files_are_refused() {
+        let header = "name,mass,x,y,z,vx,vy,vz,epoch\n";
+        let errors = [
+            (
+                "name,mass,x,y,z,vx,vy,epoch\nSun,1,0,0,0,0,0,0\n",
+                "no 'vz' column",
+            ),
+            ("name,mass (AU),x,y,z,vx,vy,vz,epoch\n", "does not fit"),
+            ("name,mass,x (furlong),y,z,vx,vy,vz,epoch\n", "Unknown unit"),
+            ("# only a comment\n", "no header"),
+            (header, "no bodies"),
+        ];
+        for (text, expected) in errors {
+            let err = Ephemeris::parse(text).unwrap_err();
+            assert!(err.contains(expected), "'{}' for {}", err, text);
+        }
+        let err = Ephemeris::parse(&format!("{}Sun,1,0,zero,0,0,0,0,0\n", header)).unwrap_err();
+        assert!(
+            err.starts_with("Line 2:") && err.contains("'zero'"),
+            "{}",
+            err
+        );
+        let err = Ephemeris::parse(&format!("{}Sun,1,0,0\n", header)).unwrap_err();
+        assert!(err.contains("Missing value"), "{}", err);
+    }
+
+    #[test]
+    fn states_that_cant_be_simulated_are_refused() {
+        let header = "name,mass,x,y,z,vx,vy,vz,epoch\nSun,2e30,0,0,0,0,0,0,0\n";
+        let errors = [
+            (
+                "Rock,nan,1,0,0,0,0,0,0",
+                "Line 3: Invalid value 'nan' for 'mass'",
+            ),
+            (
+                "Rock,1,inf,0,0,0,0,0,0",
+                "Line 3: Invalid value 'inf' for 'x'",
+            ),
+            (
+                "Rock,1,0,0,0,0,-NaN,0,0",
+                "Line 3: Invalid value '-NaN' for 'vy'",
+            ),
+            ("Rock,0,1,0,0,0,0,0,0", "Line 3: Mass must be above 0"),
+            ("Rock,-5,1,0,0,0,0,0,0", "Line 3: Mass must be above 0"),
+        ];
+        for (line, expected) in errors {
+            let err = Ephemeris::parse(&format!("{}{}\n", header, line)).unwrap_err();
+            assert!(err.starts_with(expected), "'{}' for {}", err, line);
+        }
+    }
+
+    #[test]
+    fn planets_are_barycentric_and_flattened() {
+        // A pair orbiting in a plane tilted about the x axis
+        let text = "name,mass (Msun),x (AU),y (AU),z (AU),vx,vy,vz,epoch\n\
+                    A,1,0,0,0,0,0,0,0\n\
+                    B,1,2,0,0,0,20,20,0\n";
+        let ephemeris = Ephemeris::parse(text).unwrap();
+        let units = UnitSystem::solar(120.0, UnitStyle::Astronomical);
+        let config = PlanetConfig::new(0.0, 800.0, 0.0, 1.0, 0.0, 1.0);
+        let planets =
+            ephemeris.to_planets(&units, Projection::Invariable, &config, [400.0, 400.0], 3);
+        assert_eq!((planets[0].id(), planets[1].id()), (3, 4));
+        for (planet, x) in planets.iter().zip([200.0, 600.0]) {
+            assert!((planet.pos()[0] - x).abs() < 1e-9 && (planet.pos()[1] - 400.0).abs() < 1e-9);
+        }
+        // The whole tilted velocity lies in the invariable plane
+        let speed = units.to_sim(20_000.0 * 2.0_f64.sqrt() / 2.0, Dimension::Velocity);
+        assert!((planets[1].vel()[1].abs() - speed).abs() < 1e-9);
+        let flat = ephemeris.to_planets(&units, Projection::ReferencePlane, &config, [0.0; 2], 0);
+        assert!(flat[1].vel()[1].abs() < planets[1].vel()[1].abs());
+    }
+}
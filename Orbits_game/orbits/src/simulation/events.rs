@@ -0,0 +1,322 @@
+use super::orbit::OrbitalElements;
+use super::units::{self, Dimension, UnitSystem};
+use crate::celestial_bodies::body_config::CelestialBody;
+use crate::celestial_bodies::planet::Planet;
+use crate::utils::array_logic::{subtract_arrays, Length};
+use std::collections::{HashMap, VecDeque};
+
+// Events kept when nobody drains the bus, the oldest are dropped first
+const BUS_CAPACITY: usize = 1024;
+// Default distance between centres below which a close approach is reported
+const CLOSE_APPROACH_DISTANCE: f64 = 12.0;
+// Bodies only count as captured or escaped once their specific orbital
+// energy is this fraction of the local potential away from zero, so
+// orbits close to parabolic do not flip back and forth every step
+const BINDING_MARGIN: f64 = 0.05;
+
+/// Something interesting that happened in the simulation
+#[derive(Debug, Clone, PartialEq)]
+pub enum SimEvent {
+    /// Two bodies passed closest to each other, closer than the threshold
+    CloseApproach {
+        time: f64,
+        a: u32,
+        b: u32,
+        distance: f64,
+    },
+    /// Two bodies merged, the survivor keeps its id
+    Collision {
+        time: f64,
+        survivor: u32,
+        absorbed: u32,
+    },
+    /// A body was torn into fragments within the Roche limit of the primary
+    Disruption {
+        time: f64,
+        id: u32,
+        fragments: usize,
+    },
+    /// A body became unbound from the primary
+    Escape { time: f64, id: u32 },
+    /// A body became bound to the primary
+    Capture { time: f64, id: u32 },
+    /// A body crossed the boundary and was put back at random
+    BoundaryReset { time: f64, id: u32 },
+}
+
+impl SimEvent {
+    pub fn time(&self) -> f64 {
+        match *self {
+            SimEvent::CloseApproach { time, .. }
+            | SimEvent::Collision { time, .. }
+            | SimEvent::Disruption { time, .. }
+            | SimEvent::Escape { time, .. }
+            | SimEvent::Capture { time, .. }
+            | SimEvent::BoundaryReset { time, .. } => time,
+        }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            SimEvent::CloseApproach { .. } => "close_approach",
+            SimEvent::Collision { .. } => "collision",
+            SimEvent::Disruption { .. } => "disruption",
+            SimEvent::Escape { .. } => "escape",
+            SimEvent::Capture { .. } => "capture",
+            SimEvent::BoundaryReset { .. } => "boundary_reset",
+        }
+    }
+
+    /// One line describing the event, with units when given
+    pub fn describe(&self, units: Option<&UnitSystem>) -> String {
+        let time = units::show(units, self.time(), Dimension::Time);
+        let what = match *self {
+            SimEvent::CloseApproach { a, b, distance, .. } => format!(
+                "close approach of #{} and #{} at {}",
+                a,
+                b,
+                units::show(units, distance, Dimension::Length)
+            ),
+            SimEvent::Collision {
+                survivor, absorbed, ..
+            } => format!("#{} absorbed #{}", survivor, absorbed),
+            SimEvent::Disruption { id, fragments, .. } => {
+                format!("#{} torn into {} fragments", id, fragments)
+            }
+            SimEvent::Escape { id, .. } => format!("#{} escaped", id),
+            SimEvent::Capture { id, .. } => format!("#{} was captured", id),
+            SimEvent::BoundaryReset { id, .. } => {
+                format!("#{} left the boundary and was reset", id)
+            }
+        };
+        format!("t={} {}", time, what)
+    }
+}
+
+/// Queue the simulation emits events into and the GUI or the
+/// headless log takes them from. Holds at most a fixed number
+/// of events, so a bus that is never drained does not grow
+#[derive(Debug, Clone, Default)]
+pub struct EventBus {
+    queue: VecDeque<SimEvent>,
+}
+
+impl EventBus {
+    pub fn new() -> EventBus {
+        EventBus {
+            queue: VecDeque::new(),
+        }
+    }
+
+    pub fn emit(&mut self, event: SimEvent) {
+        if self.queue.len() == BUS_CAPACITY {
+            self.queue.pop_front();
+        }
+        self.queue.push_back(event);
+    }
+
+    /// Take every event emitted since the last drain, oldest first
+    pub fn drain(&mut self) -> Vec<SimEvent> {
+        self.queue.drain(..).collect()
+    }
+}
+
+/// An ongoing close encounter of a pair of bodies
+#[derive(Debug, Clone, Copy)]
+struct Encounter {
+    closest: f64,
+    /// Whether the pair came closer while in range, pairs that only move
+    /// apart, such as fresh fragments, are not approaching anything
+    approaching: bool,
+    reported: bool,
+}
+
+/// Watches the bodies from step to step for close approaches and
+/// for changes in whether they are bound to the primary
+#[derive(Debug, Clone)]
+pub struct EventDetector {
+    pub enabled: bool,
+    /// Distance between centres below which an approach counts as close
+    pub close_approach: f64,
+    encounters: HashMap<(u32, u32), Encounter>,
+    bound: HashMap<u32, bool>,
+}
+
+impl EventDetector {
+    pub fn new() -> EventDetector {
+        EventDetector {
+            enabled: true,
+            close_approach: CLOSE_APPROACH_DISTANCE,
+            encounters: HashMap::new(),
+            bound: HashMap::new(),
+        }
+    }
+
+    /// Compare the bodies after a step with what was seen before
+    pub fn observe(
+        &mut self,
+        planets: &[Planet],
+        primary: Option<usize>,
+        g: f64,
+        time: f64,
+        bus: &mut EventBus,
+    ) {
+        if !self.enabled {
+            return;
+        }
+        self.observe_approaches(planets, time, bus);
+        if let Some(primary) = primary {
+            self.observe_binding(planets, primary, g, time, bus);
+        }
+    }
+
+    /// Report each close encounter once, when the pair is
+    /// closest and just starts moving apart again
+    fn observe_approaches(&mut self, planets: &[Planet], time: f64, bus: &mut EventBus) {
+        let mut encounters = HashMap::new();
+        for i in 0..planets.len() {
+            for j in (i + 1)..planets.len() {
+                let (a, b) = (&planets[i], &planets[j]);
+                let distance = subtract_arrays(a.pos(), b.pos()).get_length();
+                if distance >= self.close_approach {
+                    continue;
+                }
+                let key = (a.id().min(b.id()), a.id().max(b.id()));
+                let mut encounter = self.encounters.get(&key).copied().unwrap_or(Encounter {
+                    closest: distance,
+                    approaching: false,
+                    reported: false,
+                });
+                encounter.approaching |= distance < encounter.closest;
+                if distance > encounter.closest && encounter.approaching && !encounter.reported {
+                    bus.emit(SimEvent::CloseApproach {
+                        time,
+                        a: key.0,
+                        b: key.1,
+                        distance: encounter.closest,
+                    });
+                    encounter.reported = true;
+                }
+                encounter.closest = encounter.closest.min(distance);
+                encounters.insert(key, encounter);
+            }
+        }
+        // Pairs that moved out of range are forgotten, so they are reported again next time
+        self.encounters = encounters;
+    }
+
+    fn observe_binding(
+        &mut self,
+        planets: &[Planet],
+        primary: usize,
+        g: f64,
+        time: f64,
+        bus: &mut EventBus,
+    ) {
+        let primary = &planets[primary];
+        for planet in planets.iter().filter(|planet| planet.id() != primary.id()) {
+            let elements = OrbitalElements::between(planet, primary, g);
+            let distance = subtract_arrays(planet.pos(), primary.pos()).get_length();
+            let margin = BINDING_MARGIN * elements.mu / distance;
+            let bound = if elements.energy < -margin {
+                true
+            } else if elements.energy > margin {
+                false
+            } else {
+                continue;
+            };
+            // The first sighting of a body only sets where it starts
+            match self.bound.insert(planet.id(), bound) {
+                Some(false) if bound => bus.emit(SimEvent::Capture {
+                    time,
+                    id: planet.id(),
+                }),
+                Some(true) if !bound => bus.emit(SimEvent::Escape {
+                    time,
+                    id: planet.id(),
+                }),
+                _ => {}
+            }
+        }
+    }
+
+    /// Forget what was seen of a body, e.g. after it was reset
+    pub fn forget(&mut self, id: u32) {
+        self.bound.remove(&id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::celestial_bodies::body_config::PlanetConfig;
+    use crate::utils::colour;
+
+    fn body(id: u32, pos: [f64; 2], vel: [f64; 2], mass: f64) -> Planet {
+        let config = PlanetConfig::new(0.0, 800.0, 0.0, 1.0, 0.0, 1.0);
+        Planet::from_state(&config, id, colour::WHITE, pos, vel, mass)
+    }
+
+    #[test]
+    fn close_approach_is_reported_once_at_its_closest() {
+        let mut detector = EventDetector::new();
+        let mut bus = EventBus::new();
+        let still = body(3, [0.0, 0.0], [0.0; 2], 1.0);
+        for (step, x) in [20.0, 10.0, 6.0, 5.0, 7.0, 9.0, 6.5, 11.0, 20.0]
+            .into_iter()
+            .enumerate()
+        {
+            let passing = body(1, [x, 0.0], [0.0; 2], 1.0);
+            detector.observe(&[still.clone(), passing], None, 1.0, step as f64, &mut bus);
+        }
+        assert_eq!(
+            bus.drain(),
+            [SimEvent::CloseApproach {
+                time: 4.0,
+                a: 1,
+                b: 3,
+                distance: 5.0
+            }]
+        );
+    }
+
+    #[test]
+    fn escape_and_capture_are_reported_when_the_binding_changes() {
+        let mut detector = EventDetector::new();
+        let mut bus = EventBus::new();
+        let primary = body(0, [0.0, 0.0], [0.0; 2], 1000.0);
+        // The circular speed is about 3.2 and the escape speed about 4.5
+        for (step, speed) in [3.0, 3.0, 6.0, 4.47, 6.0, 4.47, 3.0]
+            .into_iter()
+            .enumerate()
+        {
+            let planet = body(1, [100.0, 0.0], [0.0, speed], 1.0);
+            detector.observe(
+                &[primary.clone(), planet],
+                Some(0),
+                1.0,
+                step as f64,
+                &mut bus,
+            );
+        }
+        assert_eq!(
+            bus.drain(),
+            [
+                SimEvent::Escape { time: 2.0, id: 1 },
+                SimEvent::Capture { time: 6.0, id: 1 }
+            ]
+        );
+    }
+
+    #[test]
+    fn bus_drops_the_oldest_events() {
+        let mut bus = EventBus::new();
+        for id in 0..BUS_CAPACITY as u32 + 2 {
+            bus.emit(SimEvent::BoundaryReset { time: 0.0, id });
+        }
+        let events = bus.drain();
+        assert_eq!(events.len(), BUS_CAPACITY);
+        assert_eq!(events[0], SimEvent::BoundaryReset { time: 0.0, id: 2 });
+        assert!(bus.drain().is_empty());
+    }
+}
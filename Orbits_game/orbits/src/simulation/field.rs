@@ -0,0 +1,100 @@
+use super::world::World;
+use crate::celestial_bodies::body_config::CelestialBody;
+
+// Small enough to not matter for the reduced mass of the 1PN term
+const PROBE_MASS: f64 = 1e-6;
+
+/// A test particle at rest, used to measure the
+/// field of the bodies without disturbing it
+pub struct Probe {
+    pos: [f64; 2],
+}
+
+impl Probe {
+    pub fn at(pos: [f64; 2]) -> Probe {
+        Probe { pos }
+    }
+}
+
+impl CelestialBody for Probe {
+    fn mass(&self) -> f64 {
+        PROBE_MASS
+    }
+
+    fn pos(&self) -> [f64; 2] {
+        self.pos
+    }
+
+    fn vel(&self) -> [f64; 2] {
+        [0.0, 0.0]
+    }
+}
+
+/// What a field grid samples on its nodes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldQuantity {
+    /// `World::acceleration_at`, Newtonian gravity with the 1PN term when enabled
+    Acceleration,
+    /// `World::potential_at`, Newtonian gravity only
+    Potential,
+}
+
+/// Field of the world sampled on the nodes of a regular grid,
+/// only the sampled quantity is filled in, the other stays empty
+pub struct FieldGrid {
+    pub origin: [f64; 2],
+    pub spacing: f64,
+    /// Number of nodes along x and y
+    pub nodes: [usize; 2],
+    pub acceleration: Vec<[f64; 2]>,
+    pub potential: Vec<f64>,
+}
+
+impl FieldGrid {
+    /// Sample the quantity over an area of `size` starting at
+    /// `origin`, with a node every `spacing` units on both edges
+    pub fn sample(
+        world: &World,
+        quantity: FieldQuantity,
+        origin: [f64; 2],
+        size: [f64; 2],
+        spacing: f64,
+    ) -> FieldGrid {
+        let nodes = [
+            (size[0] / spacing).floor() as usize + 1,
+            (size[1] / spacing).floor() as usize + 1,
+        ];
+        let mut grid = FieldGrid {
+            origin,
+            spacing,
+            nodes,
+            acceleration: Vec::new(),
+            potential: Vec::new(),
+        };
+        for y in 0..nodes[1] {
+            for x in 0..nodes[0] {
+                let pos = grid.position(x, y);
+                match quantity {
+                    FieldQuantity::Acceleration => {
+                        grid.acceleration.push(world.acceleration_at(pos))
+                    }
+                    FieldQuantity::Potential => grid.potential.push(world.potential_at(pos)),
+                }
+            }
+        }
+        grid
+    }
+
+    /// World position of the node at column x and row y
+    pub fn position(&self, x: usize, y: usize) -> [f64; 2] {
+        [
+            self.origin[0] + x as f64 * self.spacing,
+            self.origin[1] + y as f64 * self.spacing,
+        ]
+    }
+
+    /// Index of the node at column x and row y into the sample vectors
+    pub fn index(&self, x: usize, y: usize) -> usize {
+        y * self.nodes[0] + x
+    }
+}
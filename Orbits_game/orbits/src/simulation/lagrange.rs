@@ -0,0 +1,180 @@
+use crate::celestial_bodies::body_config::CelestialBody;
+use crate::celestial_bodies::planet::Planet;
+use crate::utils::array_logic::{add_arrays, cross_product, scalar_mult, subtract_arrays, Length};
+
+// Ratio of the Roche limit to the primary's radius for a fluid satellite
+// of equal density, a rigid one survives down to about 1.26
+const ROCHE_FLUID: f64 = 2.44;
+const ROOT_ITERATIONS: usize = 100;
+
+/// Lagrange points, Hill sphere and Roche limit of a secondary
+/// body around its primary, in the restricted three-body problem.
+/// All values use the current separation of the pair, so they
+/// follow the bodies around as they move
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PairGeometry {
+    pub barycentre: [f64; 2],
+    pub separation: f64,
+    /// Mass of the secondary over the total mass
+    pub mass_ratio: f64,
+    /// L1 to L5 in world coordinates, L4 leads the secondary along its orbit
+    pub lagrange: [[f64; 2]; 5],
+    /// Radius around the secondary within which it dominates
+    /// the primary's gravity and can hold on to satellites
+    pub hill_radius: f64,
+    /// Distance from the primary below which the secondary
+    /// is torn apart by tides
+    pub roche_limit: f64,
+}
+
+impl PairGeometry {
+    /// The geometry of `secondary` orbiting `primary`, None when the
+    /// bodies overlap or the pair has no positive mass
+    pub fn between(primary: &Planet, secondary: &Planet) -> Option<PairGeometry> {
+        let offset = subtract_arrays(secondary.pos(), primary.pos());
+        let separation = offset.get_length();
+        let total_mass = primary.mass() + secondary.mass();
+        if separation <= 0.0 || total_mass <= 0.0 || secondary.mass() < 0.0 {
+            return None;
+        }
+        let mu = secondary.mass() / total_mass;
+        let barycentre = add_arrays(primary.pos(), scalar_mult(offset, mu));
+
+        // Unit vectors along the pair and perpendicular to it, the
+        // latter pointing along the motion of the secondary
+        let along = scalar_mult(offset, 1.0 / separation);
+        let rel_vel = subtract_arrays(secondary.vel(), primary.vel());
+        let sense = if cross_product(offset, rel_vel) < 0.0 {
+            -1.0
+        } else {
+            1.0
+        };
+        let across = scalar_mult([-along[1], along[0]], sense);
+
+        // Positions in units of the separation, relative to the barycentre
+        let to_world = |x: f64, y: f64| {
+            let point = add_arrays(
+                scalar_mult(along, x * separation),
+                scalar_mult(across, y * separation),
+            );
+            add_arrays(barycentre, point)
+        };
+        let l1 = collinear_point(mu, -mu, 1.0 - mu);
+        let l2 = collinear_point(mu, 1.0 - mu, 2.0);
+        let l3 = collinear_point(mu, -2.0, -mu);
+        let height = 3.0_f64.sqrt() / 2.0;
+        let lagrange = [
+            to_world(l1, 0.0),
+            to_world(l2, 0.0),
+            to_world(l3, 0.0),
+            to_world(0.5 - mu, height),
+            to_world(0.5 - mu, -height),
+        ];
+
+        Some(PairGeometry {
+            barycentre,
+            separation,
+            mass_ratio: mu,
+            lagrange,
+            hill_radius: separation
+                * (secondary.mass() / (3.0 * primary.mass().max(f64::MIN_POSITIVE))).cbrt(),
+            roche_limit: roche_limit(primary, secondary),
+        })
+    }
+
+    /// Whether the secondary is within the primary's Roche limit
+    pub fn within_roche_limit(&self) -> bool {
+        self.separation < self.roche_limit
+    }
+}
+
+/// Distance from `primary` within which `secondary`, held together
+/// only by its own gravity, is disrupted by tides
+pub fn roche_limit(primary: &Planet, secondary: &Planet) -> f64 {
+    ROCHE_FLUID * primary.radius() * (primary.density() / secondary.density()).cbrt()
+}
+
+/// Net acceleration along the axis in the rotating frame, in units of
+/// the separation with the primary at -mu and the secondary at 1 - mu
+fn axis_acceleration(mu: f64, x: f64) -> f64 {
+    let to_primary = x + mu;
+    let to_secondary = x - 1.0 + mu;
+    x - (1.0 - mu) * to_primary / to_primary.abs().powi(3)
+        - mu * to_secondary / to_secondary.abs().powi(3)
+}
+
+/// Find the collinear point strictly between `low` and `high` by
+/// bisection, the acceleration changes sign exactly once there
+fn collinear_point(mu: f64, low: f64, high: f64) -> f64 {
+    // Step off the bodies themselves, where the acceleration diverges
+    let (mut low, mut high) = (low + 1e-9, high - 1e-9);
+    let low_sign = axis_acceleration(mu, low).signum();
+    for _ in 0..ROOT_ITERATIONS {
+        let mid = 0.5 * (low + high);
+        if axis_acceleration(mu, mid).signum() == low_sign {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    0.5 * (low + high)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::celestial_bodies::body_config::PlanetConfig;
+    use crate::utils::colour;
+
+    // Mass ratio of the Earth-Moon system, whose collinear points are
+    // tabulated at 0.836915, 1.155682 and -1.005063 from the barycentre
+    const EARTH_MOON: f64 = 0.012150585;
+
+    fn body(pos: [f64; 2], vel: [f64; 2], mass: f64, density: f64) -> Planet {
+        let config = PlanetConfig::new(0.0, 800.0, 0.0, mass, 0.0, density);
+        Planet::from_state(&config, 0, colour::WHITE, pos, vel, mass)
+    }
+
+    fn assert_close(point: [f64; 2], expected: [f64; 2]) {
+        let distance = subtract_arrays(point, expected).get_length();
+        assert!(distance < 1e-3, "{:?} is not {:?}", point, expected);
+    }
+
+    #[test]
+    fn earth_moon_lagrange_points() {
+        let primary = body([0.0, 0.0], [0.0, 0.0], 1000.0 * (1.0 - EARTH_MOON), 1.0);
+        let secondary = body([100.0, 0.0], [0.0, 10.0], 1000.0 * EARTH_MOON, 1.0);
+        let pair = PairGeometry::between(&primary, &secondary).unwrap();
+
+        let x = 100.0 * EARTH_MOON;
+        assert!((pair.mass_ratio - EARTH_MOON).abs() < 1e-12);
+        assert_close(pair.barycentre, [x, 0.0]);
+        assert_close(pair.lagrange[0], [x + 83.6915, 0.0]);
+        assert_close(pair.lagrange[1], [x + 115.5682, 0.0]);
+        assert_close(pair.lagrange[2], [x - 100.5063, 0.0]);
+        // L4 and L5 form equilateral triangles with the pair, L4 ahead
+        let height = 50.0 * 3.0_f64.sqrt();
+        assert_close(pair.lagrange[3], [50.0, height]);
+        assert_close(pair.lagrange[4], [50.0, -height]);
+    }
+
+    #[test]
+    fn l4_leads_a_clockwise_orbit() {
+        let primary = body([0.0, 0.0], [0.0, 0.0], 990.0, 1.0);
+        let secondary = body([100.0, 0.0], [0.0, -10.0], 10.0, 1.0);
+        let pair = PairGeometry::between(&primary, &secondary).unwrap();
+        assert!(pair.lagrange[3][1] < 0.0 && pair.lagrange[4][1] > 0.0);
+    }
+
+    #[test]
+    fn hill_radius_and_roche_limit() {
+        let primary = body([0.0, 0.0], [0.0, 0.0], 3000.0, 1.0);
+        let secondary = body([200.0, 0.0], [0.0, 10.0], 8.0, 8.0);
+        let pair = PairGeometry::between(&primary, &secondary).unwrap();
+        // A secondary eight times as dense survives at half the distance
+        assert!((pair.hill_radius - 200.0 * (8.0_f64 / 9000.0).cbrt()).abs() < 1e-9);
+        assert!((pair.roche_limit - 0.5 * ROCHE_FLUID * primary.radius()).abs() < 1e-9);
+        assert!(!pair.within_roche_limit());
+        assert!(PairGeometry::between(&primary, &primary).is_none());
+    }
+}
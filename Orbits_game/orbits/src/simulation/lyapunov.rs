@@ -0,0 +1,205 @@
+use super::units::{self, Dimension};
+use super::world::World;
+use crate::celestial_bodies::body_config::CelestialBody;
+
+// Initial distance in phase space between the world and its shadow
+const INITIAL_SEPARATION: f64 = 1e-6;
+// Steps between renormalisations of the separation
+const RENORMALISE_INTERVAL: u64 = 10;
+
+/// Estimates the maximal Lyapunov exponent of the world by stepping a
+/// shadow copy that starts a tiny distance away in phase space. Every
+/// few steps the log of the growth of the separation is summed and the
+/// shadow is pulled back to the initial distance, along the direction
+/// it diverged in (Benettin's method)
+pub struct LyapunovEstimator {
+    shadow: World,
+    log_growth: f64,
+    /// Simulated time the growth was summed over
+    elapsed: f64,
+    /// Time of the world at the last renormalisation
+    renormalised_at: f64,
+    renormalisations: u64,
+}
+
+/// The current estimate
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LyapunovEstimate {
+    /// Mean exponential rate of divergence per unit of time
+    pub exponent: f64,
+    /// Time over which nearby states diverge by a factor e, 1 / exponent
+    pub lyapunov_time: f64,
+    pub elapsed: f64,
+}
+
+impl LyapunovEstimator {
+    /// Start estimating from the current state of the world
+    pub fn start(world: &World) -> LyapunovEstimator {
+        let mut shadow = world.fork();
+        // Events of the shadow are of no interest
+        shadow.detector.enabled = false;
+        if let Some(planet) = shadow.planets.first_mut() {
+            let pos = planet.pos();
+            planet.set_position([pos[0] + INITIAL_SEPARATION, pos[1]]);
+        }
+        LyapunovEstimator {
+            shadow,
+            log_growth: 0.0,
+            elapsed: 0.0,
+            renormalised_at: world.time,
+            renormalisations: 0,
+        }
+    }
+
+    /// Step the shadow along with the world, call this after every step
+    /// of the world. Starts over when the world jumped to another state
+    /// (e.g. a loaded snapshot) or the worlds no longer match body for
+    /// body, e.g. after a collision happened in only one of them
+    pub fn observe(&mut self, world: &World) {
+        if self.shadow.steps + 1 != world.steps {
+            *self = LyapunovEstimator::start(world);
+            return;
+        }
+        self.shadow.settings = world.settings.clone();
+        self.shadow.advance();
+        if self.shadow.planets.len() != world.planets.len() {
+            *self = LyapunovEstimator::start(world);
+            return;
+        }
+        if !world.steps.is_multiple_of(RENORMALISE_INTERVAL) {
+            return;
+        }
+
+        let separation = phase_distance(world, &self.shadow);
+        if separation <= 0.0 || !separation.is_finite() {
+            return;
+        }
+        self.log_growth += (separation / INITIAL_SEPARATION).ln();
+        self.elapsed += world.time - self.renormalised_at;
+        self.renormalised_at = world.time;
+        self.renormalisations += 1;
+
+        // Pull the shadow back towards the world, keeping the direction
+        let scale = INITIAL_SEPARATION / separation;
+        for (shadow, planet) in self.shadow.planets.iter_mut().zip(world.planets.iter()) {
+            let pull = |a: [f64; 2], b: [f64; 2]| {
+                [b[0] + (a[0] - b[0]) * scale, b[1] + (a[1] - b[1]) * scale]
+            };
+            shadow.set_position(pull(shadow.pos(), planet.pos()));
+            shadow.set_velocity(pull(shadow.vel(), planet.vel()));
+        }
+        // The forces gathered for the next step were those at the old positions
+        self.shadow.refresh_forces();
+    }
+
+    pub fn estimate(&self) -> Option<LyapunovEstimate> {
+        if self.renormalisations == 0 || self.elapsed <= 0.0 {
+            return None;
+        }
+        let exponent = self.log_growth / self.elapsed;
+        Some(LyapunovEstimate {
+            exponent,
+            lyapunov_time: 1.0 / exponent,
+            elapsed: self.elapsed,
+        })
+    }
+
+    /// Text lines describing the estimate, with units when the world has them
+    pub fn lines(&self, world: &World) -> Vec<String> {
+        let estimate = match self.estimate() {
+            Some(estimate) => estimate,
+            None => return vec!["lyapunov     warming up".to_string()],
+        };
+        let show = |value| units::show(world.settings.units.as_ref(), value, Dimension::Time);
+        let mut lines = vec![format!(
+            "lyapunov     {:.4} / {}",
+            estimate.exponent,
+            show(1.0)
+        )];
+        if estimate.exponent > 0.0 {
+            lines.push(format!("divergence   {}", show(estimate.lyapunov_time)));
+        } else {
+            lines.push("divergence   none (regular)".to_string());
+        }
+        lines.push(format!("measured     {}", show(estimate.elapsed)));
+        lines
+    }
+}
+
+/// Euclidean distance between two worlds over the positions
+/// and velocities of all bodies
+fn phase_distance(a: &World, b: &World) -> f64 {
+    let mut sum = 0.0;
+    for (p, q) in a.planets.iter().zip(b.planets.iter()) {
+        for k in 0..2 {
+            sum += (p.pos()[k] - q.pos()[k]).powi(2) + (p.vel()[k] - q.vel()[k]).powi(2);
+        }
+    }
+    sum.sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::celestial_bodies::body_config::PlanetConfig;
+    use crate::celestial_bodies::planet::Planet;
+    use crate::simulation::settings::SimSettings;
+    use crate::simulation::world::{ExternalForce, SimRng};
+    use crate::utils::colour;
+    use rand::SeedableRng;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    struct Wind;
+
+    impl ExternalForce for Wind {
+        fn forces(&mut self, planets: &[Planet], _: f64) -> Vec<[f64; 2]> {
+            vec![[5.0, 0.0]; planets.len()]
+        }
+    }
+
+    #[test]
+    fn kepler_orbit_is_regular() {
+        // Nearby orbits only drift apart linearly, so the
+        // estimate falls towards zero as the run goes on
+        let config = PlanetConfig::new(0.0, 800.0, 0.0, 1.0, 0.0, 1.0);
+        let primary =
+            Planet::from_state(&config, 0, colour::WHITE, [400.0, 400.0], [0.0; 2], 1000.0);
+        let body = Planet::from_state(&config, 1, colour::WHITE, [500.0, 400.0], [0.0, 30.0], 1.0);
+        let mut world = World::new(
+            vec![primary, body],
+            [400.0, 400.0],
+            SimSettings::new(120.0),
+            SimRng::seed_from_u64(0),
+        );
+        let mut estimator = LyapunovEstimator::start(&world);
+        for _ in 0..20_000 {
+            world.advance();
+            estimator.observe(&world);
+        }
+        let estimate = estimator.estimate().unwrap();
+        assert!(estimate.exponent < 0.05);
+    }
+
+    #[test]
+    fn shadow_feels_the_same_forces() {
+        // Under a uniform force alone nothing diverges, unless
+        // the shadow were left without it
+        let config = PlanetConfig::new(0.0, 800.0, 0.0, 1.0, 0.0, 1.0);
+        let body = Planet::from_state(&config, 0, colour::WHITE, [400.0, 400.0], [0.0; 2], 1.0);
+        let mut world = World::new(
+            vec![body],
+            [400.0, 400.0],
+            SimSettings::new(120.0),
+            SimRng::seed_from_u64(0),
+        );
+        world.forces.push(Rc::new(RefCell::new(Wind)));
+        let mut estimator = LyapunovEstimator::start(&world);
+        for _ in 0..1000 {
+            world.advance();
+            estimator.observe(&world);
+        }
+        let estimate = estimator.estimate().unwrap();
+        assert!(estimate.exponent.abs() < 1e-3);
+    }
+}
@@ -0,0 +1,17 @@
+pub mod action;
+pub mod ephemeris;
+pub mod events;
+pub mod field;
+pub mod lagrange;
+pub mod lyapunov;
+pub mod observer;
+pub mod orbit;
+pub mod precession;
+pub mod recording;
+pub mod script;
+pub mod settings;
+pub mod snapshot;
+pub mod tidal;
+pub mod timeline;
+pub mod units;
+pub mod world;
@@ -0,0 +1,56 @@
+use super::world::World;
+use crate::celestial_bodies::planet::Planet;
+
+/// Callbacks on what happens in a world, for tools and game logic that
+/// follow the simulation without being part of it. Every callback does
+/// nothing unless it is implemented. Changes to the bodies made during
+/// a step are passed on at its end, before `step_end`
+pub trait WorldObserver {
+    /// The world is about to take a step
+    fn step_begin(&mut self, _world: &World) {}
+
+    /// The world took a step
+    fn step_end(&mut self, _world: &World) {}
+
+    /// A body was added, e.g. by the user or as a fragment
+    fn spawned(&mut self, _world: &World, _planet: &Planet) {}
+
+    /// A body was taken out of the world, as it was when it went
+    fn removed(&mut self, _world: &World, _planet: &Planet) {}
+
+    /// A body was put back at random, as it is after the reset
+    fn reset(&mut self, _world: &World, _planet: &Planet) {}
+
+    /// Two bodies merged, the survivor as it is after the merge
+    /// and the absorbed body as it was before
+    fn collision(&mut self, _world: &World, _survivor: &Planet, _absorbed: &Planet) {}
+
+    /// A body crossed the boundary, as it was when it left.
+    /// It is reset straight after
+    fn boundary(&mut self, _world: &World, _planet: &Planet) {}
+}
+
+/// A change to the bodies, kept until the observers are told of it
+#[derive(Debug, Clone)]
+pub enum Notice {
+    Spawned(Planet),
+    Removed(Planet),
+    Reset(Planet),
+    Collision { survivor: Planet, absorbed: Planet },
+    Boundary(Planet),
+}
+
+impl Notice {
+    /// Pass the notice on to the callback of the observer for it
+    pub fn tell(&self, observer: &mut dyn WorldObserver, world: &World) {
+        match self {
+            Notice::Spawned(planet) => observer.spawned(world, planet),
+            Notice::Removed(planet) => observer.removed(world, planet),
+            Notice::Reset(planet) => observer.reset(world, planet),
+            Notice::Collision { survivor, absorbed } => {
+                observer.collision(world, survivor, absorbed)
+            }
+            Notice::Boundary(planet) => observer.boundary(world, planet),
+        }
+    }
+}
@@ -0,0 +1,162 @@
+use crate::celestial_bodies::body_config::CelestialBody;
+use crate::utils::array_logic::{cross_product, dot_product, scalar_mult, subtract_arrays, Length};
+use std::f64::consts::PI;
+
+/// The osculating Keplerian orbit of a body around
+/// a primary, derived from their relative position
+/// and velocity at one instant
+#[derive(Debug, Clone, Copy)]
+pub struct OrbitalElements {
+    /// Standard gravitational parameter G(m + M)
+    pub mu: f64,
+    /// Negative for hyperbolic (unbound) orbits
+    pub semi_major_axis: f64,
+    pub eccentricity: f64,
+    /// Angle of the periapsis measured from the x-axis (radians)
+    pub arg_periapsis: f64,
+    pub true_anomaly: f64,
+    /// Semi-latus rectum a(1 - e^2) = h^2 / mu
+    pub semi_latus_rectum: f64,
+    /// Specific angular momentum, its sign gives the direction of travel
+    pub ang_mom: f64,
+    /// Specific orbital energy, negative when the orbit is bound
+    pub energy: f64,
+}
+
+impl OrbitalElements {
+    /// Compute the elements of `body` around `primary`
+    pub fn between<C: CelestialBody>(body: &C, primary: &C, g: f64) -> OrbitalElements {
+        let rel_pos = subtract_arrays(body.pos(), primary.pos());
+        let rel_vel = subtract_arrays(body.vel(), primary.vel());
+        OrbitalElements::from_state(rel_pos, rel_vel, g * (body.mass() + primary.mass()))
+    }
+
+    /// Compute the elements from a relative position and velocity
+    pub fn from_state(rel_pos: [f64; 2], rel_vel: [f64; 2], mu: f64) -> OrbitalElements {
+        let dist = rel_pos.get_length();
+        let speed_sqr = dot_product(rel_vel, rel_vel);
+        let ang_mom = cross_product(rel_pos, rel_vel);
+        let energy = 0.5 * speed_sqr - mu / dist;
+
+        // The eccentricity (Laplace-Runge-Lenz) vector points at periapsis
+        let ecc = scalar_mult(
+            subtract_arrays(
+                scalar_mult(rel_pos, speed_sqr - mu / dist),
+                scalar_mult(rel_vel, dot_product(rel_pos, rel_vel)),
+            ),
+            1.0 / mu,
+        );
+        let arg_periapsis = ecc[1].atan2(ecc[0]);
+        let true_anomaly = rel_pos[1].atan2(rel_pos[0]) - arg_periapsis;
+
+        OrbitalElements {
+            mu,
+            semi_major_axis: -mu / (2.0 * energy),
+            eccentricity: ecc.get_length(),
+            arg_periapsis,
+            true_anomaly,
+            semi_latus_rectum: ang_mom * ang_mom / mu,
+            ang_mom,
+            energy,
+        }
+    }
+
+    pub fn is_bound(&self) -> bool {
+        self.energy < 0.0
+    }
+
+    /// Orbital period, only defined for bound orbits
+    pub fn period(&self) -> Option<f64> {
+        if !self.is_bound() {
+            return None;
+        }
+        Some(2.0 * PI * (self.semi_major_axis.powi(3) / self.mu).sqrt())
+    }
+
+    pub fn periapsis(&self) -> f64 {
+        self.semi_latus_rectum / (1.0 + self.eccentricity)
+    }
+
+    /// Apoapsis distance, only defined for bound orbits
+    pub fn apoapsis(&self) -> Option<f64> {
+        if !self.is_bound() {
+            return None;
+        }
+        Some(self.semi_latus_rectum / (1.0 - self.eccentricity))
+    }
+
+    /// Distance from the primary at a given true anomaly,
+    /// None where a hyperbola never reaches
+    pub fn radius_at(&self, true_anomaly: f64) -> Option<f64> {
+        let denom = 1.0 + self.eccentricity * true_anomaly.cos();
+        if denom <= 0.0 {
+            return None;
+        }
+        Some(self.semi_latus_rectum / denom)
+    }
+
+    /// Position on the conic at a given true anomaly,
+    /// relative to the primary
+    pub fn position_at(&self, true_anomaly: f64) -> Option<[f64; 2]> {
+        let radius = self.radius_at(true_anomaly)?;
+        let angle = true_anomaly + self.arg_periapsis;
+        Some([radius * angle.cos(), radius * angle.sin()])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MU: f64 = 1000.0;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9 * b.abs().max(1.0)
+    }
+
+    #[test]
+    fn circular_orbit() {
+        let orbit = OrbitalElements::from_state([100.0, 0.0], [0.0, 10f64.sqrt()], MU);
+        assert!(orbit.is_bound());
+        assert!(close(orbit.semi_major_axis, 100.0));
+        assert!(orbit.eccentricity < 1e-9);
+        assert!(close(orbit.periapsis(), 100.0));
+        assert!(close(orbit.apoapsis().unwrap(), 100.0));
+        assert!(close(orbit.period().unwrap(), 2.0 * PI * 1000f64.sqrt()));
+        assert!(orbit.ang_mom > 0.0);
+    }
+
+    #[test]
+    fn eccentric_orbit_points_at_its_periapsis() {
+        // At periapsis straight up the y-axis, a = 100 and e = 0.5
+        let orbit = OrbitalElements::from_state([0.0, 50.0], [-(30f64.sqrt()), 0.0], MU);
+        assert!(close(orbit.semi_major_axis, 100.0));
+        assert!(close(orbit.eccentricity, 0.5));
+        assert!(close(orbit.periapsis(), 50.0));
+        assert!(close(orbit.apoapsis().unwrap(), 150.0));
+        assert!(close(orbit.arg_periapsis, PI / 2.0));
+        assert!(orbit.true_anomaly.abs() < 1e-9);
+        let apoapsis = orbit.position_at(PI).unwrap();
+        assert!(apoapsis[0].abs() < 1e-9 && close(apoapsis[1], -150.0));
+
+        // The same orbit travelled the other way round
+        let reversed = OrbitalElements::from_state([0.0, 50.0], [30f64.sqrt(), 0.0], MU);
+        assert!(reversed.ang_mom < 0.0);
+        assert!(close(reversed.arg_periapsis, PI / 2.0));
+    }
+
+    #[test]
+    fn hyperbolic_orbit_is_unbound() {
+        let orbit = OrbitalElements::from_state([100.0, 0.0], [0.0, 6.0], MU);
+        assert!(!orbit.is_bound());
+        assert!(close(orbit.energy, 8.0));
+        assert!(close(orbit.semi_major_axis, -62.5));
+        assert!(close(orbit.eccentricity, 2.6));
+        assert!(close(orbit.periapsis(), 100.0));
+        assert_eq!(orbit.apoapsis(), None);
+        assert_eq!(orbit.period(), None);
+        // Beyond the asymptotes the hyperbola never reaches
+        assert_eq!(orbit.radius_at(PI), None);
+        assert!(orbit.radius_at(PI / 2.0).is_some());
+    }
+}
@@ -0,0 +1,127 @@
+use super::orbit::OrbitalElements;
+use super::world::World;
+use crate::celestial_bodies::body_config::CelestialBody;
+use crate::utils::array_logic::{dot_product, subtract_arrays};
+use std::f64::consts::PI;
+
+/// A single periapsis passage of the tracked body,
+/// along with the advance of the periapsis since the
+/// previous passage (radians, positive is prograde)
+#[derive(Debug, Clone, Copy)]
+pub struct PrecessionSample {
+    pub time: f64,
+    pub advance: f64,
+    /// Analytic 1PN advance per orbit, 6*pi*GM / (c^2 a (1 - e^2)),
+    /// only available when the correction is switched on
+    pub predicted: Option<f64>,
+}
+
+/// Diagnostic that follows one body around a primary
+/// and measures how far its periapsis moves every orbit.
+/// Both are kept by id, as bodies may come and go
+pub struct PeriapsisTracker {
+    pub body: u32,
+    pub primary: u32,
+    last_radial_vel: Option<f64>,
+    last_angle: Option<f64>,
+}
+
+impl PeriapsisTracker {
+    pub fn new(body: u32, primary: u32) -> PeriapsisTracker {
+        PeriapsisTracker {
+            body,
+            primary,
+            last_radial_vel: None,
+            last_angle: None,
+        }
+    }
+
+    /// Inspect the world after a step, returns a sample
+    /// whenever the body has just passed its periapsis
+    pub fn observe(&mut self, world: &World) -> Option<PrecessionSample> {
+        let body = &world.planets[world.index_of(self.body)?];
+        let primary = &world.planets[world.index_of(self.primary)?];
+        let rel_pos = subtract_arrays(body.pos(), primary.pos());
+        let rel_vel = subtract_arrays(body.vel(), primary.vel());
+
+        // Periapsis is where the radial velocity turns from inwards to outwards
+        let radial_vel = dot_product(rel_pos, rel_vel);
+        let passed = matches!(self.last_radial_vel, Some(last) if last < 0.0 && radial_vel >= 0.0);
+        self.last_radial_vel = Some(radial_vel);
+        if !passed {
+            return None;
+        }
+
+        let elements = OrbitalElements::between(body, primary, world.settings.grav_const);
+        let angle = elements.arg_periapsis;
+        let last_angle = self.last_angle.replace(angle)?;
+
+        // Wrap to (-pi, pi] and orient along the direction of travel
+        let mut advance = angle - last_angle;
+        while advance > PI {
+            advance -= 2.0 * PI;
+        }
+        while advance <= -PI {
+            advance += 2.0 * PI;
+        }
+        advance *= elements.ang_mom.signum();
+
+        let predicted = world
+            .settings
+            .speed_of_light
+            .map(|c| 6.0 * PI * elements.mu / (c * c * elements.semi_latus_rectum));
+
+        Some(PrecessionSample {
+            time: world.time,
+            advance,
+            predicted,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::celestial_bodies::body_config::PlanetConfig;
+    use crate::celestial_bodies::planet::Planet;
+    use crate::simulation::settings::{Integrator, SimSettings};
+    use crate::simulation::world::SimRng;
+    use crate::utils::colour;
+    use rand::SeedableRng;
+
+    #[test]
+    fn measured_precession_matches_1pn_prediction() {
+        // A light body on an orbit with a = 100 and e = 0.5 around
+        // a heavy one, starting at periapsis where it moves at 60
+        let config = PlanetConfig::new(0.0, 800.0, 0.0, 1.0, 0.0, 1.0);
+        let primary =
+            Planet::from_state(&config, 0, colour::WHITE, [400.0, 400.0], [0.0; 2], 1000.0);
+        let body = Planet::from_state(&config, 1, colour::WHITE, [450.0, 400.0], [0.0, 60.0], 1e-6);
+        let mut settings = SimSettings::new(120.0);
+        settings.speed_of_light = Some(1500.0);
+        settings.integrator = Integrator::VelocityVerlet;
+        let mut world = World::new(
+            vec![primary, body],
+            [400.0, 400.0],
+            settings,
+            SimRng::seed_from_u64(0),
+        );
+
+        let mut tracker = PeriapsisTracker::new(1, 0);
+        let mut samples = Vec::new();
+        while samples.len() < 3 {
+            world.step(1.0 / 2000.0);
+            samples.extend(tracker.observe(&world));
+        }
+        for sample in samples {
+            let predicted = sample.predicted.unwrap();
+            let error = (sample.advance - predicted).abs() / predicted;
+            assert!(
+                error < 0.02,
+                "advance {} against {}",
+                sample.advance,
+                predicted
+            );
+        }
+    }
+}
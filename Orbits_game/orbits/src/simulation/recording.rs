@@ -0,0 +1,194 @@
+use super::action::Action;
+use super::snapshot::Snapshot;
+use super::world::World;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Bumped whenever the layout of a recording changes
+pub const RECORDING_VERSION: u32 = 3;
+
+/// An action along with the step it was made before
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedAction {
+    pub step: u64,
+    pub action: Action,
+}
+
+/// A recorded session, the initial state of the world and
+/// every action applied to it. Replaying the actions at the
+/// same steps reproduces the session exactly
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Recording {
+    pub version: u32,
+    pub initial: Snapshot,
+    pub actions: Vec<RecordedAction>,
+    /// Step the recording was stopped at
+    pub end_step: u64,
+    /// Fingerprint of the world at the end, used to
+    /// check that a replay ended up in the same state
+    pub fingerprint: u64,
+}
+
+impl Recording {
+    /// Stored with bincode rather than json, as recordings of
+    /// long sessions can hold a lot of actions
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let contents = bincode::serialize(self).map_err(to_io_error)?;
+        fs::write(path, contents)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Recording> {
+        let contents = fs::read(path)?;
+        let recording: Recording = bincode::deserialize(&contents).map_err(to_io_error)?;
+        if recording.version != RECORDING_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Recording version {} is not supported (expected {})",
+                    recording.version, RECORDING_VERSION
+                ),
+            ));
+        }
+        Ok(recording)
+    }
+}
+
+fn to_io_error(err: bincode::Error) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+/// Records the actions applied to a world
+pub struct Recorder {
+    initial: Snapshot,
+    actions: Vec<RecordedAction>,
+}
+
+impl Recorder {
+    /// Start recording from the current state of the world
+    pub fn start(world: &World) -> Recorder {
+        Recorder {
+            initial: Snapshot::capture(world),
+            actions: Vec::new(),
+        }
+    }
+
+    /// Record an action that is about to be applied
+    pub fn record(&mut self, world: &World, action: &Action) {
+        self.actions.push(RecordedAction {
+            step: world.steps,
+            action: action.clone(),
+        });
+    }
+
+    /// Stop recording, the world is in its final state
+    pub fn finish(self, world: &World) -> Recording {
+        Recording {
+            version: RECORDING_VERSION,
+            initial: self.initial,
+            actions: self.actions,
+            end_step: world.steps,
+            fingerprint: world.fingerprint(),
+        }
+    }
+}
+
+/// Plays a recording back into a world
+pub struct Replay {
+    recording: Recording,
+    next_action: usize,
+}
+
+impl Replay {
+    /// Start a replay, returning the world to step forward with it
+    pub fn start(recording: Recording) -> (Replay, World) {
+        let world = recording.initial.restore();
+        let replay = Replay {
+            recording,
+            next_action: 0,
+        };
+        (replay, world)
+    }
+
+    /// Apply the actions that were made before the coming step,
+    /// call this before every step of the world
+    pub fn apply_due(&mut self, world: &mut World) {
+        let actions = &self.recording.actions;
+        while self.next_action < actions.len() && actions[self.next_action].step <= world.steps {
+            actions[self.next_action].action.apply(world);
+            self.next_action += 1;
+        }
+    }
+
+    pub fn is_finished(&self, world: &World) -> bool {
+        world.steps >= self.recording.end_step
+    }
+
+    /// Whether the world ended up in the recorded final state
+    pub fn matches(&self, world: &World) -> bool {
+        world.fingerprint() == self.recording.fingerprint
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::celestial_bodies::body_config::PlanetConfig;
+    use crate::celestial_bodies::planet::Planet;
+    use crate::simulation::settings::{Integrator, SimSettings};
+    use crate::simulation::world::SimRng;
+    use crate::utils::colour;
+    use rand::SeedableRng;
+    use std::env;
+
+    #[test]
+    fn recording_replays_to_the_same_state() {
+        let mut rng = SimRng::seed_from_u64(3);
+        let config = PlanetConfig::new(0.0, 800.0, 20.0, 10.0, 2.0, 0.2);
+        let planets = (0..20)
+            .map(|id| Planet::new(&config, id, colour::WHITE, &mut rng))
+            .collect();
+        let mut world = World::new(planets, [400.0, 400.0], SimSettings::new(120.0), rng);
+
+        let mut recorder = Recorder::start(&world);
+        let actions = [
+            (
+                50,
+                Action::SetMass {
+                    index: 2,
+                    mass: 40.0,
+                },
+            ),
+            (120, Action::SetIntegrator(Integrator::VelocityVerlet)),
+            (
+                200,
+                Action::SetVelocity {
+                    index: 5,
+                    velocity: [3.0, -4.0],
+                },
+            ),
+            (260, Action::Remove { index: 0 }),
+        ];
+        let mut pending = actions.iter().peekable();
+        while world.steps < 400 {
+            while let Some((_, action)) = pending.next_if(|(step, _)| *step == world.steps) {
+                recorder.record(&world, action);
+                action.apply(&mut world);
+            }
+            world.advance();
+        }
+        let path = env::temp_dir().join("orbits_recording_replay.bin");
+        recorder.finish(&world).save(&path).unwrap();
+        let recording = Recording::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let (mut replay, mut replayed) = Replay::start(recording);
+        while !replay.is_finished(&replayed) {
+            replay.apply_due(&mut replayed);
+            replayed.advance();
+        }
+        assert!(replay.matches(&replayed));
+        assert_eq!(replayed.fingerprint(), world.fingerprint());
+    }
+}
@@ -0,0 +1,596 @@
+use super::observer::{Notice, WorldObserver};
+use super::world::{ExternalForce, World};
+use crate::celestial_bodies::body_config::{CelestialBody, PlanetConfig};
+use crate::celestial_bodies::planet::Planet;
+use crate::utils::colour;
+use rhai::{Array, CallFnOptions, Dynamic, Engine, EvalAltResult, Scope, AST, INT};
+use std::cell::{Cell, RefCell};
+use std::mem;
+use std::path::Path;
+use std::rc::Rc;
+
+// Functions a script may define, with their number of parameters
+const ON_STEP: (&str, usize) = ("on_step", 0);
+const FORCE: (&str, usize) = ("force", 1);
+const ON_SPAWN: (&str, usize) = ("on_spawn", 1);
+const ON_REMOVE: (&str, usize) = ("on_remove", 1);
+const ON_RESET: (&str, usize) = ("on_reset", 1);
+const ON_BOUNDARY: (&str, usize) = ("on_boundary", 1);
+const ON_COLLISION: (&str, usize) = ("on_collision", 2);
+
+/// A body as a script sees it, a copy of its state
+/// which is written back to the world with `update`
+#[derive(Debug, Clone)]
+pub struct Body {
+    id: INT,
+    x: f64,
+    y: f64,
+    vx: f64,
+    vy: f64,
+    mass: f64,
+    radius: f64,
+}
+
+impl Body {
+    fn of(planet: &Planet) -> Body {
+        let (pos, vel) = (planet.pos(), planet.vel());
+        Body {
+            id: planet.id() as INT,
+            x: pos[0],
+            y: pos[1],
+            vx: vel[0],
+            vy: vel[1],
+            mass: planet.mass(),
+            radius: planet.radius(),
+        }
+    }
+}
+
+/// What the functions bound into the script work on. The world
+/// lends its bodies to it for as long as the script runs
+struct Shared {
+    planets: Vec<Planet>,
+    time: f64,
+    steps: u64,
+    centre: [f64; 2],
+    grav_const: f64,
+    /// Configuration of the bodies the script adds, and the id of the next
+    planet_const: PlanetConfig,
+    next_id: u32,
+    /// Whether the script changed the bodies
+    edited: bool,
+    /// Ids of the bodies the script added, and the bodies it removed
+    added: Vec<u32>,
+    removed: Vec<Planet>,
+}
+
+impl Shared {
+    fn find(&mut self, id: INT) -> Result<&mut Planet, Box<EvalAltResult>> {
+        self.planets
+            .iter_mut()
+            .find(|planet| planet.id() as INT == id)
+            .ok_or_else(|| format!("There is no body #{}", id).into())
+    }
+
+    fn take_out(&mut self, index: usize) {
+        let planet = self.planets.remove(index);
+        // A body added and removed again never was in the world
+        match self.added.iter().position(|&id| id == planet.id()) {
+            Some(added) => {
+                self.added.remove(added);
+            }
+            None => self.removed.push(planet),
+        }
+        self.edited = true;
+    }
+}
+
+/// A Rhai script that sets up a scenario and steers the simulation.
+/// Its top level runs once against the world, after which its `on_step()`
+/// is called after every step and its `force(body)`, returning `[fx, fy]`,
+/// adds to the force of gravity on every body. It can also follow the
+/// bodies with `on_spawn(body)`, `on_remove(body)`, `on_reset(body)`,
+/// `on_boundary(body)` and `on_collision(survivor, absorbed)`
+pub struct Script {
+    engine: Engine,
+    ast: AST,
+    shared: Rc<RefCell<Shared>>,
+    /// Set after an error, the script is not run again after one
+    failed: Cell<bool>,
+}
+
+impl Script {
+    /// Compile the script, bodies it adds follow the configuration
+    pub fn load<P: AsRef<Path>>(
+        path: P,
+        planet_const: &PlanetConfig,
+    ) -> Result<Rc<Script>, String> {
+        let shared = Rc::new(RefCell::new(Shared {
+            planets: Vec::new(),
+            time: 0.0,
+            steps: 0,
+            centre: [0.0, 0.0],
+            grav_const: 0.0,
+            planet_const: planet_const.clone(),
+            next_id: 0,
+            edited: false,
+            added: Vec::new(),
+            removed: Vec::new(),
+        }));
+        let engine = engine(&shared);
+        let ast = engine
+            .compile_file(path.as_ref().to_path_buf())
+            .map_err(|err| err.to_string())?;
+        Ok(Rc::new(Script {
+            engine,
+            ast,
+            shared,
+            failed: Cell::new(false),
+        }))
+    }
+
+    /// Run the top level of the script to set the world up, and add its
+    /// force and its callbacks to the world when it defines them
+    pub fn setup(self: &Rc<Self>, world: &mut World) -> Result<(), String> {
+        self.lend(world);
+        let result = self.engine.run_ast(&self.ast);
+        self.give_back(world);
+        result.map_err(|err| err.to_string())?;
+        if self.defines(FORCE) {
+            world
+                .forces
+                .push(Rc::new(RefCell::new(ScriptForce(Rc::clone(self)))));
+        }
+        let callbacks = [ON_SPAWN, ON_REMOVE, ON_RESET, ON_BOUNDARY, ON_COLLISION];
+        if callbacks.into_iter().any(|callback| self.defines(callback)) {
+            world
+                .observers
+                .push(Box::new(ScriptObserver(Rc::clone(self))));
+        }
+        Ok(())
+    }
+
+    /// Call `on_step()` after the world took a step
+    pub fn step(&self, world: &mut World) {
+        if self.failed.get() || !self.defines(ON_STEP) {
+            return;
+        }
+        self.lend(world);
+        let result = self.call(ON_STEP.0, ());
+        self.give_back(world);
+        if let Err(err) = result {
+            self.fail(ON_STEP.0, &err);
+        }
+    }
+
+    fn defines(&self, (name, params): (&str, usize)) -> bool {
+        self.ast
+            .iter_functions()
+            .any(|function| function.name == name && function.params.len() == params)
+    }
+
+    /// Call a function of the script without running its top level again
+    fn call(&self, name: &str, args: impl rhai::FuncArgs) -> Result<Dynamic, Box<EvalAltResult>> {
+        let options = CallFnOptions::new().eval_ast(false);
+        self.engine
+            .call_fn_with_options(options, &mut Scope::new(), &self.ast, name, args)
+    }
+
+    fn fail(&self, function: &str, err: &EvalAltResult) {
+        eprintln!("Script error in {}, stopping the script: {}", function, err);
+        self.failed.set(true);
+    }
+
+    fn lend(&self, world: &mut World) {
+        let mut shared = self.shared.borrow_mut();
+        shared.next_id = world.next_id();
+        shared.planets = mem::take(&mut world.planets);
+        shared.time = world.time;
+        shared.steps = world.steps;
+        shared.centre = world.centre;
+        shared.grav_const = world.settings.grav_const;
+        shared.edited = false;
+    }
+
+    fn give_back(&self, world: &mut World) {
+        let (planets, added, removed, edited) = {
+            let mut shared = self.shared.borrow_mut();
+            let added = mem::take(&mut shared.added);
+            let (added, planets): (Vec<Planet>, Vec<Planet>) = mem::take(&mut shared.planets)
+                .into_iter()
+                .partition(|planet| added.contains(&planet.id()));
+            (
+                planets,
+                added,
+                mem::take(&mut shared.removed),
+                shared.edited,
+            )
+        };
+        // The observers of the world, this script among them, hear of
+        // what it added and removed once the bodies are back in the world
+        world.planets = planets;
+        for planet in removed {
+            world.detector.forget(planet.id());
+            world.notice(|_| Notice::Removed(planet));
+        }
+        for planet in added {
+            world.spawn(planet);
+        }
+        // Bodies changed by the script make the old energy meaningless
+        if edited {
+            world.rebase_energy();
+        }
+    }
+
+    /// Run a function of the script that may look at
+    /// the bodies of the world but not change them
+    fn looking_at<T>(&self, planets: &[Planet], time: f64, run: impl FnOnce() -> T) -> T {
+        {
+            let mut shared = self.shared.borrow_mut();
+            shared.planets = planets.to_vec();
+            shared.time = time;
+        }
+        let result = run();
+        let mut shared = self.shared.borrow_mut();
+        shared.planets.clear();
+        shared.added.clear();
+        shared.removed.clear();
+        result
+    }
+
+    /// Call one of the callbacks following the bodies, when it is defined
+    fn notify(&self, world: &World, callback: (&str, usize), args: impl rhai::FuncArgs) {
+        if self.failed.get() || !self.defines(callback) {
+            return;
+        }
+        let result = self.looking_at(&world.planets, world.time, || self.call(callback.0, args));
+        if let Err(err) = result {
+            self.fail(callback.0, &err);
+        }
+    }
+}
+
+/// The force a script defines, applied by the world
+struct ScriptForce(Rc<Script>);
+
+impl ExternalForce for ScriptForce {
+    fn forces(&mut self, planets: &[Planet], time: f64) -> Vec<[f64; 2]> {
+        let script = &self.0;
+        if script.failed.get() {
+            return vec![[0.0, 0.0]; planets.len()];
+        }
+        let forces: Result<Vec<[f64; 2]>, Box<EvalAltResult>> =
+            script.looking_at(planets, time, || {
+                planets
+                    .iter()
+                    .map(|planet| vector(&script.call(FORCE.0, (Body::of(planet),))?))
+                    .collect()
+            });
+        forces.unwrap_or_else(|err| {
+            script.fail(FORCE.0, &err);
+            vec![[0.0, 0.0]; planets.len()]
+        })
+    }
+}
+
+/// The callbacks of a script following the bodies
+struct ScriptObserver(Rc<Script>);
+
+impl WorldObserver for ScriptObserver {
+    fn spawned(&mut self, world: &World, planet: &Planet) {
+        self.0.notify(world, ON_SPAWN, (Body::of(planet),));
+    }
+
+    fn removed(&mut self, world: &World, planet: &Planet) {
+        self.0.notify(world, ON_REMOVE, (Body::of(planet),));
+    }
+
+    fn reset(&mut self, world: &World, planet: &Planet) {
+        self.0.notify(world, ON_RESET, (Body::of(planet),));
+    }
+
+    fn collision(&mut self, world: &World, survivor: &Planet, absorbed: &Planet) {
+        self.0.notify(
+            world,
+            ON_COLLISION,
+            (Body::of(survivor), Body::of(absorbed)),
+        );
+    }
+
+    fn boundary(&mut self, world: &World, planet: &Planet) {
+        self.0.notify(world, ON_BOUNDARY, (Body::of(planet),));
+    }
+}
+
+/// A number given as either a float or an integer
+fn number(value: &Dynamic) -> Result<f64, Box<EvalAltResult>> {
+    value
+        .as_float()
+        .or_else(|_| value.as_int().map(|value| value as f64))
+        .map_err(|kind| format!("Expected a number, got {}", kind).into())
+}
+
+/// A vector given as an array of two numbers
+fn vector(value: &Dynamic) -> Result<[f64; 2], Box<EvalAltResult>> {
+    match value.clone().try_cast::<Array>() {
+        Some(array) if array.len() == 2 => Ok([number(&array[0])?, number(&array[1])?]),
+        _ => Err(format!("Expected an array [x, y], got {}", value).into()),
+    }
+}
+
+/// Refuse a body the simulation can't go on with, e.g. one without mass
+fn check_state(
+    position: [f64; 2],
+    velocity: [f64; 2],
+    mass: f64,
+) -> Result<(), Box<EvalAltResult>> {
+    if !position
+        .iter()
+        .chain(velocity.iter())
+        .all(|value| value.is_finite())
+    {
+        return Err(format!(
+            "Position and velocity must be finite, got ({}, {}) and ({}, {})",
+            position[0], position[1], velocity[0], velocity[1]
+        )
+        .into());
+    }
+    if !(mass.is_finite() && mass > 0.0) {
+        return Err(format!("Mass must be a positive number, got {}", mass).into());
+    }
+    Ok(())
+}
+
+/// An engine with the functions scripts use to work on the world
+fn engine(shared: &Rc<RefCell<Shared>>) -> Engine {
+    let mut engine = Engine::new();
+    // Debug builds of Rhai allow only half as deeply nested expressions
+    engine.set_max_expr_depths(64, 32);
+
+    engine
+        .register_type_with_name::<Body>("Body")
+        .register_get("id", |body: &mut Body| body.id)
+        .register_get("x", |body: &mut Body| body.x)
+        .register_get("y", |body: &mut Body| body.y)
+        .register_get("vx", |body: &mut Body| body.vx)
+        .register_get("vy", |body: &mut Body| body.vy)
+        .register_get("mass", |body: &mut Body| body.mass)
+        .register_get("radius", |body: &mut Body| body.radius)
+        .register_set("x", |body: &mut Body, value: Dynamic| {
+            number(&value).map(|value| body.x = value)
+        })
+        .register_set("y", |body: &mut Body, value: Dynamic| {
+            number(&value).map(|value| body.y = value)
+        })
+        .register_set("vx", |body: &mut Body, value: Dynamic| {
+            number(&value).map(|value| body.vx = value)
+        })
+        .register_set("vy", |body: &mut Body, value: Dynamic| {
+            number(&value).map(|value| body.vy = value)
+        })
+        .register_set("mass", |body: &mut Body, value: Dynamic| {
+            number(&value).map(|value| body.mass = value)
+        })
+        .register_fn("to_string", |body: &mut Body| {
+            format!(
+                "#{} at ({:.2}, {:.2}) mass {:.2}",
+                body.id, body.x, body.y, body.mass
+            )
+        });
+
+    let world = Rc::clone(shared);
+    engine.register_fn("time", move || world.borrow().time);
+    let world = Rc::clone(shared);
+    engine.register_fn("steps", move || world.borrow().steps as INT);
+    let world = Rc::clone(shared);
+    engine.register_fn("grav_const", move || world.borrow().grav_const);
+    let world = Rc::clone(shared);
+    engine.register_fn("centre", move || {
+        let centre = world.borrow().centre;
+        vec![Dynamic::from(centre[0]), Dynamic::from(centre[1])]
+    });
+    let world = Rc::clone(shared);
+    engine.register_fn("planets", move || {
+        let world = world.borrow();
+        world
+            .planets
+            .iter()
+            .map(|planet| Dynamic::from(planet.id() as INT))
+            .collect::<Array>()
+    });
+    let world = Rc::clone(shared);
+    engine.register_fn("get", move |id: INT| {
+        world.borrow_mut().find(id).map(|planet| Body::of(planet))
+    });
+
+    // Writes the position, velocity and mass of a body back
+    let world = Rc::clone(shared);
+    engine.register_fn("update", move |body: Body| {
+        let (position, velocity) = ([body.x, body.y], [body.vx, body.vy]);
+        check_state(position, velocity, body.mass)?;
+        let mut world = world.borrow_mut();
+        let planet = world.find(body.id)?;
+        planet.set_position(position);
+        planet.set_velocity(velocity);
+        planet.set_mass(body.mass);
+        world.edited = true;
+        Ok::<(), Box<EvalAltResult>>(())
+    });
+    let world = Rc::clone(shared);
+    engine.register_fn(
+        "add_planet",
+        move |x: Dynamic, y: Dynamic, vx: Dynamic, vy: Dynamic, mass: Dynamic| {
+            let (position, velocity) = ([number(&x)?, number(&y)?], [number(&vx)?, number(&vy)?]);
+            let mass = number(&mass)?;
+            check_state(position, velocity, mass)?;
+            let mut world = world.borrow_mut();
+            let id = world.next_id;
+            world.next_id += 1;
+            let planet = Planet::from_state(
+                &world.planet_const,
+                id,
+                colour::for_id(id as u64),
+                position,
+                velocity,
+                mass,
+            );
+            world.planets.push(planet);
+            world.added.push(id);
+            world.edited = true;
+            Ok::<INT, Box<EvalAltResult>>(id as INT)
+        },
+    );
+    let world = Rc::clone(shared);
+    engine.register_fn("remove_planet", move |id: INT| {
+        let mut world = world.borrow_mut();
+        match world
+            .planets
+            .iter()
+            .position(|planet| planet.id() as INT == id)
+        {
+            Some(index) => {
+                world.take_out(index);
+                true
+            }
+            None => false,
+        }
+    });
+    let world = Rc::clone(shared);
+    engine.register_fn("clear", move || {
+        let mut world = world.borrow_mut();
+        for index in (0..world.planets.len()).rev() {
+            world.take_out(index);
+        }
+    });
+
+    engine
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::settings::SimSettings;
+    use crate::simulation::world::SimRng;
+    use rand::SeedableRng;
+    use std::env;
+    use std::fs;
+
+    fn config() -> PlanetConfig {
+        PlanetConfig::new(0.0, 800.0, 0.0, 1.0, 0.0, 1.0)
+    }
+
+    /// A world of resting bodies with ids 0, 1, ...
+    fn world(count: u32) -> World {
+        let planets = (0..count)
+            .map(|id| {
+                let pos = [300.0 + 100.0 * id as f64, 400.0];
+                Planet::from_state(&config(), id, colour::WHITE, pos, [0.0; 2], 1.0)
+            })
+            .collect();
+        World::new(
+            planets,
+            [400.0, 400.0],
+            SimSettings::new(120.0),
+            SimRng::seed_from_u64(0),
+        )
+    }
+
+    fn script(name: &str, source: &str) -> Rc<Script> {
+        let path = env::temp_dir().join(format!("orbits_script_{}.rhai", name));
+        fs::write(&path, source).unwrap();
+        let script = Script::load(&path, &config()).unwrap();
+        fs::remove_file(&path).unwrap();
+        script
+    }
+
+    fn ids(world: &World) -> Vec<u32> {
+        world.planets.iter().map(|planet| planet.id()).collect()
+    }
+
+    #[test]
+    fn setup_adds_and_removes_bodies() {
+        let mut world = world(2);
+        let source = "remove_planet(0); let id = add_planet(100, 200, 1.5, 0, 4); add_planet(0, 0, 0, 0, 1); remove_planet(id);";
+        script("setup", source).setup(&mut world).unwrap();
+        assert_eq!(ids(&world), [1, 3]);
+        assert!(world.forces.is_empty() && world.observers.is_empty());
+    }
+
+    #[test]
+    fn on_step_edits_the_bodies() {
+        let mut world = world(1);
+        let source =
+            "fn on_step() { let body = get(0); body.vx = steps(); body.mass = 2; update(body); }";
+        let script = script("on_step", source);
+        script.setup(&mut world).unwrap();
+        world.advance();
+        script.step(&mut world);
+        assert_eq!(world.planets[0].vel(), [1.0, 0.0]);
+        assert_eq!(world.planets[0].mass(), 2.0);
+    }
+
+    #[test]
+    fn force_is_applied() {
+        let mut world = world(1);
+        script("force", "fn force(body) { [0, 3 * body.mass] }")
+            .setup(&mut world)
+            .unwrap();
+        assert_eq!(world.forces.len(), 1);
+        for _ in 0..120 {
+            world.advance();
+        }
+        // One second under an acceleration of 3
+        let vel = world.planets[0].vel();
+        assert_eq!(vel[0], 0.0);
+        assert!((vel[1] - 3.0).abs() < 0.05, "{:?}", vel);
+    }
+
+    #[test]
+    fn failing_step_is_disabled() {
+        let mut world = world(1);
+        let source =
+            "fn on_step() { let body = get(0); body.vx += 1.0; update(body); throw \"broken\"; }";
+        let script = script("failing_step", source);
+        script.setup(&mut world).unwrap();
+        for _ in 0..3 {
+            world.advance();
+            script.step(&mut world);
+        }
+        // Only the first call was made, its edit is kept
+        assert!(script.failed.get());
+        assert_eq!(world.planets[0].vel(), [1.0, 0.0]);
+    }
+
+    #[test]
+    fn failing_force_is_dropped() {
+        let mut world = world(1);
+        let script = script("failing_force", "fn force(body) { \"not a vector\" }");
+        script.setup(&mut world).unwrap();
+        for _ in 0..3 {
+            world.advance();
+        }
+        assert!(script.failed.get());
+        assert_eq!(world.planets[0].vel(), [0.0, 0.0]);
+    }
+
+    #[test]
+    fn bodies_the_world_cant_simulate_are_refused() {
+        for source in [
+            "add_planet(0, 0, 0, 0, 0);",
+            "add_planet(0, 0, 0, 0, -2);",
+            "add_planet(parse_float(\"inf\"), 0, 0, 0, 1);",
+            "let body = get(0); body.mass = parse_float(\"NaN\"); update(body);",
+            "let body = get(0); body.vy = parse_float(\"inf\"); update(body);",
+        ] {
+            let mut world = world(1);
+            assert!(
+                script("refused", source).setup(&mut world).is_err(),
+                "{}",
+                source
+            );
+            assert_eq!(ids(&world), [0]);
+            assert_eq!(world.planets[0].mass(), 1.0);
+            assert!(world.planets[0].vel().iter().all(|value| *value == 0.0));
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use super::units::UnitSystem;
+use serde::{Deserialize, Serialize};
+
+/// Scheme used to advance positions and velocities
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Integrator {
+    /// Kick then drift with the forces of the previous step
+    SymplecticEuler,
+    /// Half kick, drift, new forces, half kick (leapfrog)
+    VelocityVerlet,
+}
+
+impl Integrator {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Integrator::SymplecticEuler => "symplectic Euler",
+            Integrator::VelocityVerlet => "velocity Verlet",
+        }
+    }
+
+    /// The next integrator in the list, wrapping around
+    pub fn next(&self) -> Integrator {
+        match self {
+            Integrator::SymplecticEuler => Integrator::VelocityVerlet,
+            Integrator::VelocityVerlet => Integrator::SymplecticEuler,
+        }
+    }
+}
+
+/// Settings that control the physics of the
+/// simulation, shared by every body in the world
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SimSettings {
+    pub grav_const: f64,
+    /// Speed of light in simulation units, when set the
+    /// first post-Newtonian correction is added to gravity
+    pub speed_of_light: Option<f64>,
+    pub integrator: Integrator,
+    /// Simulated seconds per real second
+    pub time_scale: f64,
+    /// Break up bodies that cross the Roche limit of the heaviest body
+    pub tidal_disruption: bool,
+    /// Merge bodies that touch into one
+    pub collisions: bool,
+    /// Physical units of the simulation, when unset
+    /// values are shown in simulation units
+    pub units: Option<UnitSystem>,
+}
+
+impl SimSettings {
+    pub fn new(grav_const: f64) -> SimSettings {
+        SimSettings {
+            grav_const,
+            speed_of_light: None,
+            integrator: Integrator::SymplecticEuler,
+            time_scale: 1.0,
+            tidal_disruption: false,
+            collisions: false,
+            units: None,
+        }
+    }
+}
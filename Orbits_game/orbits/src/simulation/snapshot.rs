@@ -0,0 +1,129 @@
+use super::settings::SimSettings;
+use super::world::{SimRng, World};
+use crate::celestial_bodies::planet::Planet;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Bumped whenever the layout of a snapshot changes
+pub const SNAPSHOT_VERSION: u32 = 5;
+
+/// Only the version is read first, so that snapshots of
+/// another layout give a clear error
+#[derive(Deserialize)]
+struct SnapshotHeader {
+    version: u32,
+}
+
+/// The complete state of a world, which can be written
+/// to a file and restored exactly later on
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub version: u32,
+    pub time: f64,
+    pub steps: u64,
+    pub centre: [f64; 2],
+    pub settings: SimSettings,
+    pub rng: SimRng,
+    pub planets: Vec<Planet>,
+}
+
+impl Snapshot {
+    /// Take a copy of the current state of the world
+    pub fn capture(world: &World) -> Snapshot {
+        Snapshot {
+            version: SNAPSHOT_VERSION,
+            time: world.time,
+            steps: world.steps,
+            centre: world.centre,
+            settings: world.settings.clone(),
+            rng: world.rng.clone(),
+            planets: world.planets.clone(),
+        }
+    }
+
+    /// Build a world that continues from this snapshot
+    pub fn restore(&self) -> World {
+        let mut world = World::new(
+            self.planets.clone(),
+            self.centre,
+            self.settings.clone(),
+            self.rng.clone(),
+        );
+        world.time = self.time;
+        world.steps = self.steps;
+        world
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let contents = serde_json::to_string_pretty(self)?;
+        fs::write(path, contents)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Snapshot> {
+        let contents = fs::read_to_string(path)?;
+        let header: SnapshotHeader = serde_json::from_str(&contents)?;
+        if header.version != SNAPSHOT_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Snapshot version {} is not supported (expected {})",
+                    header.version, SNAPSHOT_VERSION
+                ),
+            ));
+        }
+        Ok(serde_json::from_str(&contents)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::celestial_bodies::body_config::PlanetConfig;
+    use crate::utils::colour;
+    use rand::SeedableRng;
+    use std::env;
+
+    fn world(seed: u64) -> World {
+        let mut rng = SimRng::seed_from_u64(seed);
+        let config = PlanetConfig::new(0.0, 800.0, 20.0, 10.0, 2.0, 0.2);
+        let planets = (0..20)
+            .map(|id| Planet::new(&config, id, colour::WHITE, &mut rng))
+            .collect();
+        World::new(planets, [400.0, 400.0], SimSettings::new(120.0), rng)
+    }
+
+    #[test]
+    fn saved_snapshot_continues_exactly() {
+        let mut original = world(7);
+        for _ in 0..100 {
+            original.advance();
+        }
+        let path = env::temp_dir().join("orbits_snapshot_round_trip.json");
+        Snapshot::capture(&original).save(&path).unwrap();
+        let loaded = Snapshot::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let captured = serde_json::to_string(&Snapshot::capture(&original)).unwrap();
+        assert_eq!(serde_json::to_string(&loaded).unwrap(), captured);
+        let mut restored = loaded.restore();
+        assert_eq!(restored.fingerprint(), original.fingerprint());
+        for _ in 0..500 {
+            original.advance();
+            restored.advance();
+        }
+        assert_eq!(restored.fingerprint(), original.fingerprint());
+    }
+
+    #[test]
+    fn other_versions_are_refused() {
+        let mut snapshot = Snapshot::capture(&world(1));
+        snapshot.version = SNAPSHOT_VERSION + 1;
+        let path = env::temp_dir().join("orbits_snapshot_version.json");
+        snapshot.save(&path).unwrap();
+        let err = Snapshot::load(&path).unwrap_err();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,250 @@
+use super::events::SimEvent;
+use super::lagrange::roche_limit;
+use super::observer::Notice;
+use super::world::World;
+use crate::celestial_bodies::body_config::CelestialBody;
+use crate::celestial_bodies::planet::{radius_from_mass, Planet};
+use crate::utils::array_logic::{add_arrays, scalar_mult, subtract_arrays, Length};
+
+// Number of fragments a disrupted body breaks up into
+const FRAGMENTS: usize = 5;
+// Bodies whose fragments would be lighter than this stay whole,
+// which stops the fragments from breaking up forever
+const MIN_FRAGMENT_MASS: f64 = 0.5;
+// Distance between neighbouring fragments in fragment diameters,
+// above 1 so they do not touch when they are created
+const FRAGMENT_SPACING: f64 = 1.2;
+
+/// Break up every body that is within the Roche limit of the
+/// heaviest body into a string of fragments along the line to it.
+/// Returns the number of bodies disrupted
+pub fn disrupt_bodies(world: &mut World) -> usize {
+    let primary = match world.heaviest() {
+        Some(primary) => primary,
+        None => return 0,
+    };
+    let g = world.settings.grav_const;
+    let mut next_id = world.next_id();
+    let mut disrupted = Vec::new();
+    let mut fragments = Vec::new();
+    for (i, body) in world.planets.iter().enumerate() {
+        let primary_body = &world.planets[primary];
+        if i == primary || body.mass() / (FRAGMENTS as f64) < MIN_FRAGMENT_MASS {
+            continue;
+        }
+        let distance = subtract_arrays(body.pos(), primary_body.pos()).get_length();
+        if distance < roche_limit(primary_body, body) {
+            fragments.extend(fragment(body, primary_body, g, &mut next_id));
+            disrupted.push(i);
+            world.events.emit(SimEvent::Disruption {
+                time: world.time,
+                id: body.id(),
+                fragments: FRAGMENTS,
+            });
+        }
+    }
+    // Remove from the back so the other indices stay valid
+    for &i in disrupted.iter().rev() {
+        world.remove(i);
+    }
+    for fragment in fragments {
+        world.spawn(fragment);
+    }
+    disrupted.len()
+}
+
+/// Split a body into equal fragments spaced out along the line to
+/// the primary, moving apart at the rate tides stretch the body.
+/// The offsets are symmetric, so the total mass, the centre of mass
+/// and the momentum of the body are all conserved
+fn fragment(body: &Planet, primary: &Planet, g: f64, next_id: &mut u32) -> Vec<Planet> {
+    let offset = subtract_arrays(body.pos(), primary.pos());
+    let distance = offset.get_length();
+    let radial = scalar_mult(offset, 1.0 / distance);
+    let mass = body.mass() / FRAGMENTS as f64;
+    let spacing = 2.0 * radius_from_mass(mass, body.density()) * FRAGMENT_SPACING;
+    // Rate at which the tidal field pulls points along the radial line apart
+    let shear = (2.0 * g * primary.mass() / distance.powi(3)).sqrt();
+
+    (0..FRAGMENTS)
+        .map(|k| {
+            let along = (k as f64 - (FRAGMENTS - 1) as f64 / 2.0) * spacing;
+            let position = add_arrays(body.pos(), scalar_mult(radial, along));
+            let velocity = add_arrays(body.vel(), scalar_mult(radial, along * shear));
+            let id = *next_id;
+            *next_id += 1;
+            Planet::from_state(body.config(), id, body.colour(), position, velocity, mass)
+        })
+        .collect()
+}
+
+/// Merge every pair of overlapping bodies into one, conserving mass and
+/// momentum. With tidal disruption on, bodies within the Roche limit of
+/// the heaviest body do not merge, as tides would tear them apart again.
+/// Returns the number of merges
+pub fn merge_collisions(world: &mut World) -> usize {
+    let primary = world.heaviest().map(|i| world.planets[i].clone());
+    let tides = world.settings.tidal_disruption;
+    let mut merged = 0;
+    let mut i = 0;
+    while i < world.planets.len() {
+        let mut j = i + 1;
+        while j < world.planets.len() {
+            let (a, b) = (&world.planets[i], &world.planets[j]);
+            if subtract_arrays(a.pos(), b.pos()).get_length() >= a.radius() + b.radius() {
+                j += 1;
+                continue;
+            }
+            let merged_body = merge(a, b);
+            let torn_apart = match &primary {
+                Some(primary) if tides && a.id() != primary.id() && b.id() != primary.id() => {
+                    let distance = subtract_arrays(merged_body.pos(), primary.pos()).get_length();
+                    distance < roche_limit(primary, &merged_body)
+                }
+                _ => false,
+            };
+            if !torn_apart {
+                // The merged body takes the place of the first, so the
+                // absorbed one is always the second
+                if merged_body.id() != a.id() {
+                    world.planets.swap(i, j);
+                }
+                world.events.emit(SimEvent::Collision {
+                    time: world.time,
+                    survivor: merged_body.id(),
+                    absorbed: world.planets[j].id(),
+                });
+                world.planets[i] = merged_body;
+                world.notice(|world| Notice::Collision {
+                    survivor: world.planets[i].clone(),
+                    absorbed: world.planets[j].clone(),
+                });
+                world.remove(j);
+                merged += 1;
+            } else {
+                j += 1;
+            }
+        }
+        i += 1;
+    }
+    merged
+}
+
+/// A single body with the combined mass at the centre of mass, moving
+/// with the combined momentum. It keeps the identity of the heavier one
+fn merge(a: &Planet, b: &Planet) -> Planet {
+    let (heavy, light) = if a.mass() >= b.mass() { (a, b) } else { (b, a) };
+    let mass = heavy.mass() + light.mass();
+    let weighted = |x: [f64; 2], y: [f64; 2]| {
+        scalar_mult(
+            add_arrays(scalar_mult(x, heavy.mass()), scalar_mult(y, light.mass())),
+            1.0 / mass,
+        )
+    };
+    let position = weighted(heavy.pos(), light.pos());
+    let velocity = weighted(heavy.vel(), light.vel());
+    Planet::from_state(
+        heavy.config(),
+        heavy.id(),
+        heavy.colour(),
+        position,
+        velocity,
+        mass,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::celestial_bodies::body_config::PlanetConfig;
+    use crate::simulation::observer::WorldObserver;
+    use crate::simulation::settings::SimSettings;
+    use crate::simulation::world::SimRng;
+    use crate::utils::colour;
+    use rand::SeedableRng;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    struct Heard(Rc<RefCell<Vec<String>>>);
+
+    impl WorldObserver for Heard {
+        fn removed(&mut self, _: &World, planet: &Planet) {
+            self.0.borrow_mut().push(format!("removed {}", planet.id()));
+        }
+
+        fn collision(&mut self, _: &World, survivor: &Planet, absorbed: &Planet) {
+            let heard = format!("collision {} {}", survivor.id(), absorbed.id());
+            self.0.borrow_mut().push(heard);
+        }
+    }
+
+    #[test]
+    fn merge_keeps_the_heavier_body() {
+        let config = PlanetConfig::new(0.0, 800.0, 0.0, 1.0, 0.0, 1.0);
+        let light = Planet::from_state(&config, 0, colour::WHITE, [400.0, 400.0], [0.0; 2], 1.0);
+        let heavy = Planet::from_state(&config, 1, colour::WHITE, [401.0, 400.0], [0.0; 2], 3.0);
+        let mut world = World::new(
+            vec![light, heavy],
+            [400.0, 400.0],
+            SimSettings::new(120.0),
+            SimRng::seed_from_u64(0),
+        );
+        let heard = Rc::new(RefCell::new(Vec::new()));
+        world.observers.push(Box::new(Heard(Rc::clone(&heard))));
+
+        assert_eq!(merge_collisions(&mut world), 1);
+        assert_eq!(world.planets.len(), 1);
+        let survivor = &world.planets[0];
+        assert_eq!((survivor.id(), survivor.mass()), (1, 4.0));
+        assert!((survivor.pos()[0] - 400.75).abs() < 1e-9);
+        assert_eq!(*heard.borrow(), ["collision 1 0", "removed 0"]);
+    }
+
+    fn totals(world: &World) -> (f64, [f64; 2]) {
+        let mass = world.planets.iter().map(|planet| planet.mass()).sum();
+        let momentum = world.planets.iter().fold([0.0, 0.0], |sum, planet| {
+            add_arrays(sum, scalar_mult(planet.vel(), planet.mass()))
+        });
+        (mass, momentum)
+    }
+
+    #[test]
+    fn disruption_conserves_mass_and_momentum() {
+        let config = PlanetConfig::new(0.0, 800.0, 0.0, 1.0, 0.0, 1.0);
+        let planets = vec![
+            Planet::from_state(
+                &config,
+                0,
+                colour::WHITE,
+                [400.0, 400.0],
+                [1.0, 0.0],
+                1000.0,
+            ),
+            // Within the Roche limit of about 15
+            Planet::from_state(&config, 4, colour::WHITE, [408.0, 406.0], [-3.0, 8.0], 10.0),
+            Planet::from_state(&config, 2, colour::WHITE, [500.0, 400.0], [0.0, 3.0], 10.0),
+        ];
+        let mut world = World::new(
+            planets,
+            [400.0, 400.0],
+            SimSettings::new(120.0),
+            SimRng::seed_from_u64(0),
+        );
+        let (mass, momentum) = totals(&world);
+
+        assert_eq!(disrupt_bodies(&mut world), 1);
+        let ids: Vec<u32> = world.planets.iter().map(|planet| planet.id()).collect();
+        assert_eq!(ids, [0, 2, 5, 6, 7, 8, 9]);
+        let (after_mass, after_momentum) = totals(&world);
+        assert!((after_mass - mass).abs() < 1e-9);
+        for k in 0..2 {
+            assert!((after_momentum[k] - momentum[k]).abs() < 1e-9);
+        }
+        // The fragments spread along the line to the primary around the old centre
+        let fragments = &world.planets[2..];
+        let centre = fragments.iter().fold([0.0, 0.0], |sum, fragment| {
+            add_arrays(sum, scalar_mult(fragment.pos(), 1.0 / FRAGMENTS as f64))
+        });
+        assert!((centre[0] - 408.0).abs() < 1e-9 && (centre[1] - 406.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,157 @@
+use super::snapshot::Snapshot;
+use super::world::World;
+use std::collections::VecDeque;
+
+/// Bounded history of keyframes taken at a fixed step interval,
+/// so recent time can be scrubbed through and resumed from
+pub struct Timeline {
+    keyframes: VecDeque<Snapshot>,
+    /// Steps between two keyframes
+    interval: u64,
+    /// Maximum number of keyframes, the oldest are dropped first
+    capacity: usize,
+    /// Keyframe being viewed while scrubbing
+    cursor: Option<usize>,
+}
+
+impl Timeline {
+    pub fn new(interval: u64, capacity: usize) -> Timeline {
+        Timeline {
+            keyframes: VecDeque::with_capacity(capacity),
+            interval,
+            capacity,
+            cursor: None,
+        }
+    }
+
+    /// Store a keyframe of the world when one is due,
+    /// call this after every step
+    pub fn observe(&mut self, world: &World) {
+        if self.is_scrubbing() || !world.steps.is_multiple_of(self.interval) {
+            return;
+        }
+        if self.keyframes.len() == self.capacity {
+            self.keyframes.pop_front();
+        }
+        self.keyframes.push_back(Snapshot::capture(world));
+    }
+
+    pub fn is_scrubbing(&self) -> bool {
+        self.cursor.is_some()
+    }
+
+    pub fn len(&self) -> usize {
+        self.keyframes.len()
+    }
+
+    /// Index of the keyframe being viewed, the
+    /// latest one when not scrubbing
+    pub fn position(&self) -> usize {
+        self.cursor
+            .unwrap_or_else(|| self.keyframes.len().saturating_sub(1))
+    }
+
+    /// Move a number of keyframes back (negative) or forward,
+    /// returns the keyframe to show
+    pub fn scrub(&mut self, delta: isize) -> Option<&Snapshot> {
+        if self.keyframes.is_empty() {
+            return None;
+        }
+        let last = self.keyframes.len() as isize - 1;
+        let position = (self.position() as isize + delta).clamp(0, last);
+        self.cursor = Some(position as usize);
+        self.keyframes.get(position as usize)
+    }
+
+    /// Jump to a fraction (0 to 1) of the stored history,
+    /// e.g. from a slider, returns the keyframe to show
+    pub fn scrub_to(&mut self, fraction: f64) -> Option<&Snapshot> {
+        if self.keyframes.is_empty() {
+            return None;
+        }
+        let last = self.keyframes.len() - 1;
+        let position = (fraction.clamp(0.0, 1.0) * last as f64).round() as usize;
+        self.cursor = Some(position);
+        self.keyframes.get(position)
+    }
+
+    /// Continue from the keyframe being viewed, the keyframes
+    /// after it belong to a future that no longer happens
+    pub fn resume(&mut self) {
+        if let Some(cursor) = self.cursor.take() {
+            self.keyframes.truncate(cursor + 1);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.keyframes.clear();
+        self.cursor = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::settings::SimSettings;
+    use crate::simulation::world::SimRng;
+    use rand::SeedableRng;
+
+    /// A timeline with a keyframe every 2 steps, holding at most 3,
+    /// observed over 10 steps of an empty world
+    fn filled() -> Timeline {
+        let mut timeline = Timeline::new(2, 3);
+        let mut world = World::new(
+            Vec::new(),
+            [0.0; 2],
+            SimSettings::new(1.0),
+            SimRng::seed_from_u64(0),
+        );
+        for _ in 0..10 {
+            world.advance();
+            timeline.observe(&world);
+        }
+        timeline
+    }
+
+    fn steps(timeline: &Timeline) -> Vec<u64> {
+        timeline.keyframes.iter().map(|frame| frame.steps).collect()
+    }
+
+    #[test]
+    fn oldest_keyframes_are_dropped() {
+        let timeline = filled();
+        assert_eq!(steps(&timeline), [6, 8, 10]);
+        assert_eq!(timeline.position(), 2);
+        assert!(!timeline.is_scrubbing());
+    }
+
+    #[test]
+    fn scrubbing_stops_at_both_ends() {
+        let mut timeline = filled();
+        assert_eq!(timeline.scrub(-10).map(|frame| frame.steps), Some(6));
+        assert_eq!(timeline.position(), 0);
+        assert_eq!(timeline.scrub(1).map(|frame| frame.steps), Some(8));
+        assert_eq!(timeline.scrub(10).map(|frame| frame.steps), Some(10));
+        assert_eq!(timeline.position(), 2);
+        assert_eq!(timeline.scrub_to(-1.0).map(|frame| frame.steps), Some(6));
+        assert_eq!(timeline.scrub_to(2.0).map(|frame| frame.steps), Some(10));
+        assert!(Timeline::new(2, 3).scrub(-1).is_none());
+    }
+
+    #[test]
+    fn resuming_drops_the_keyframes_after_the_shown_one() {
+        let mut timeline = filled();
+        timeline.scrub(-1);
+        // No keyframes are taken while scrubbing
+        let mut world = timeline.keyframes[0].restore();
+        world.steps = 12;
+        timeline.observe(&world);
+        assert_eq!(timeline.len(), 3);
+
+        timeline.resume();
+        assert!(!timeline.is_scrubbing());
+        assert_eq!(steps(&timeline), [6, 8]);
+        timeline.observe(&world);
+        assert_eq!(steps(&timeline), [6, 8, 12]);
+    }
+}
@@ -0,0 +1,365 @@
+use serde::{Deserialize, Serialize};
+
+/// Newton's gravitational constant in m^3 kg^-1 s^-2
+pub const G_SI: f64 = 6.674_30e-11;
+pub const ASTRONOMICAL_UNIT: f64 = 1.495_978_707e11;
+pub const SOLAR_MASS: f64 = 1.988_47e30;
+pub const EARTH_MASS: f64 = 5.972_2e24;
+pub const DAY: f64 = 86_400.0;
+pub const YEAR: f64 = 365.25 * DAY;
+
+// Simulation length units (pixels) per AU, and simulation mass
+// units per solar mass, for the solar system preset. These put the
+// heavy body of the default world at about a solar mass with a
+// planet 200 pixels out on a one year orbit
+const PIXELS_PER_AU: f64 = 200.0;
+const MASS_UNITS_PER_SUN: f64 = 7000.0;
+
+/// Physical dimension of a quantity
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dimension {
+    Length,
+    Mass,
+    Time,
+    Velocity,
+    Acceleration,
+    Energy,
+}
+
+impl Dimension {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Dimension::Length => "length",
+            Dimension::Mass => "mass",
+            Dimension::Time => "time",
+            Dimension::Velocity => "velocity",
+            Dimension::Acceleration => "acceleration",
+            Dimension::Energy => "energy",
+        }
+    }
+}
+
+/// Units quantities are shown in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum UnitStyle {
+    /// Metres, kilograms and seconds
+    Si,
+    /// AU, solar masses and days
+    Astronomical,
+}
+
+/// Relates the dimensionless simulation to physical units. Lengths and
+/// masses have a chosen scale, the time scale follows from requiring
+/// the simulation's G to equal the real G in these units, so the
+/// simulation stays dimensionless while real values can be put in
+/// and read out
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct UnitSystem {
+    /// Metres per simulation length unit
+    pub length: f64,
+    /// Kilograms per simulation mass unit
+    pub mass: f64,
+    /// Seconds per simulation time unit
+    pub time: f64,
+    pub style: UnitStyle,
+}
+
+impl UnitSystem {
+    /// Scales for length and mass in SI units, `grav_const` being the
+    /// value of G inside the simulation
+    pub fn new(length: f64, mass: f64, grav_const: f64, style: UnitStyle) -> UnitSystem {
+        // G_sim = G_SI * mass * time^2 / length^3
+        let time = (grav_const * length.powi(3) / (G_SI * mass)).sqrt();
+        UnitSystem {
+            length,
+            mass,
+            time,
+            style,
+        }
+    }
+
+    /// Scales that fit the solar system on screen
+    pub fn solar(grav_const: f64, style: UnitStyle) -> UnitSystem {
+        UnitSystem::new(
+            ASTRONOMICAL_UNIT / PIXELS_PER_AU,
+            SOLAR_MASS / MASS_UNITS_PER_SUN,
+            grav_const,
+            style,
+        )
+    }
+
+    /// SI value of one simulation unit of the dimension
+    fn scale(&self, dimension: Dimension) -> f64 {
+        match dimension {
+            Dimension::Length => self.length,
+            Dimension::Mass => self.mass,
+            Dimension::Time => self.time,
+            Dimension::Velocity => self.length / self.time,
+            Dimension::Acceleration => self.length / self.time.powi(2),
+            Dimension::Energy => self.mass * (self.length / self.time).powi(2),
+        }
+    }
+
+    /// Convert a value in SI units into simulation units
+    pub fn to_sim(self, value: f64, dimension: Dimension) -> f64 {
+        value / self.scale(dimension)
+    }
+
+    /// Convert a value in simulation units into SI units
+    pub fn to_si(self, value: f64, dimension: Dimension) -> f64 {
+        value * self.scale(dimension)
+    }
+
+    /// Convert a quantity such as "1.5 AU" into simulation units
+    pub fn parse(&self, text: &str, dimension: Dimension) -> Result<f64, String> {
+        let quantity = Quantity::parse(text)?;
+        if quantity.unit.dimension() != dimension {
+            return Err(format!("Expected a {} in '{}'", dimension.name(), text));
+        }
+        Ok(self.to_sim(quantity.to_si(), dimension))
+    }
+
+    /// Show a value given in simulation units with its physical unit
+    pub fn show(&self, value: f64, dimension: Dimension) -> String {
+        let si = self.to_si(value, dimension);
+        let (value, unit) = match (self.style, dimension) {
+            (UnitStyle::Astronomical, Dimension::Length) => (si / ASTRONOMICAL_UNIT, Unit::Au),
+            (UnitStyle::Astronomical, Dimension::Mass) => (si / SOLAR_MASS, Unit::SolarMass),
+            (UnitStyle::Astronomical, Dimension::Time) if si.abs() >= YEAR => {
+                (si / YEAR, Unit::Year)
+            }
+            (UnitStyle::Astronomical, Dimension::Time) => (si / DAY, Unit::Day),
+            (UnitStyle::Astronomical, Dimension::Velocity) => {
+                (si / 1000.0, Unit::KilometresPerSecond)
+            }
+            (_, dimension) => return format!("{:.3e} {}", si, si_symbol(dimension)),
+        };
+        format!("{:.3} {}", value, unit.symbol())
+    }
+}
+
+/// Show a value with its unit when the world has a unit system,
+/// or as the raw simulation value otherwise
+pub fn show(units: Option<&UnitSystem>, value: f64, dimension: Dimension) -> String {
+    match units {
+        Some(units) => units.show(value, dimension),
+        None if dimension == Dimension::Time => format!("{:.2} s", value),
+        None => format!("{:.2}", value),
+    }
+}
+
+fn si_symbol(dimension: Dimension) -> &'static str {
+    match dimension {
+        Dimension::Length => "m",
+        Dimension::Mass => "kg",
+        Dimension::Time => "s",
+        Dimension::Velocity => "m/s",
+        Dimension::Acceleration => "m/s^2",
+        Dimension::Energy => "J",
+    }
+}
+
+/// Units that quantities can be written in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unit {
+    Metre,
+    Kilometre,
+    Au,
+    Kilogram,
+    EarthMass,
+    SolarMass,
+    Second,
+    Day,
+    Year,
+    MetresPerSecond,
+    KilometresPerSecond,
+    AuPerDay,
+}
+
+impl Unit {
+    pub fn from_symbol(symbol: &str) -> Option<Unit> {
+        let unit = match symbol {
+            "m" => Unit::Metre,
+            "km" => Unit::Kilometre,
+            "AU" | "au" => Unit::Au,
+            "kg" => Unit::Kilogram,
+            "Mearth" | "M_earth" => Unit::EarthMass,
+            "Msun" | "M_sun" => Unit::SolarMass,
+            "s" => Unit::Second,
+            "d" | "day" | "days" => Unit::Day,
+            "yr" | "year" | "years" => Unit::Year,
+            "m/s" => Unit::MetresPerSecond,
+            "km/s" => Unit::KilometresPerSecond,
+            "AU/d" | "au/d" => Unit::AuPerDay,
+            _ => return None,
+        };
+        Some(unit)
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Unit::Metre => "m",
+            Unit::Kilometre => "km",
+            Unit::Au => "AU",
+            Unit::Kilogram => "kg",
+            Unit::EarthMass => "Mearth",
+            Unit::SolarMass => "Msun",
+            Unit::Second => "s",
+            Unit::Day => "d",
+            Unit::Year => "yr",
+            Unit::MetresPerSecond => "m/s",
+            Unit::KilometresPerSecond => "km/s",
+            Unit::AuPerDay => "AU/d",
+        }
+    }
+
+    pub fn dimension(&self) -> Dimension {
+        match self {
+            Unit::Metre | Unit::Kilometre | Unit::Au => Dimension::Length,
+            Unit::Kilogram | Unit::EarthMass | Unit::SolarMass => Dimension::Mass,
+            Unit::Second | Unit::Day | Unit::Year => Dimension::Time,
+            Unit::MetresPerSecond | Unit::KilometresPerSecond | Unit::AuPerDay => {
+                Dimension::Velocity
+            }
+        }
+    }
+
+    /// Value of one of this unit in SI units
+    pub fn si_factor(&self) -> f64 {
+        match self {
+            Unit::Metre | Unit::Kilogram | Unit::Second | Unit::MetresPerSecond => 1.0,
+            Unit::Kilometre | Unit::KilometresPerSecond => 1000.0,
+            Unit::Au => ASTRONOMICAL_UNIT,
+            Unit::EarthMass => EARTH_MASS,
+            Unit::SolarMass => SOLAR_MASS,
+            Unit::Day => DAY,
+            Unit::Year => YEAR,
+            Unit::AuPerDay => ASTRONOMICAL_UNIT / DAY,
+        }
+    }
+}
+
+/// A value along with the unit it is written in
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quantity {
+    pub value: f64,
+    pub unit: Unit,
+}
+
+impl Quantity {
+    /// Parse a value followed by a unit symbol, e.g. "1.5 AU" or "30 km/s"
+    pub fn parse(text: &str) -> Result<Quantity, String> {
+        let mut parts = text.split_whitespace();
+        let (value, symbol) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(value), Some(symbol), None) => (value, symbol),
+            _ => {
+                return Err(format!(
+                    "Expected a value and a unit, e.g. '1.5 AU', in '{}'",
+                    text
+                ))
+            }
+        };
+        let value = value
+            .parse()
+            .map_err(|_| format!("Invalid number '{}' in '{}'", value, text))?;
+        let unit = Unit::from_symbol(symbol)
+            .ok_or_else(|| format!("Unknown unit '{}' in '{}'", symbol, text))?;
+        Ok(Quantity { value, unit })
+    }
+
+    pub fn to_si(self) -> f64 {
+        self.value * self.unit.si_factor()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GRAV_CONST: f64 = 120.0;
+
+    fn close(a: f64, b: f64, tolerance: f64) -> bool {
+        (a - b).abs() <= tolerance * b.abs()
+    }
+
+    #[test]
+    fn parse_quantities() {
+        let quantity = Quantity::parse(" 1.5  AU ").unwrap();
+        assert_eq!(quantity.unit, Unit::Au);
+        assert_eq!(quantity.to_si(), 1.5 * ASTRONOMICAL_UNIT);
+        assert_eq!(Quantity::parse("30 km/s").unwrap().to_si(), 30_000.0);
+        assert_eq!(Quantity::parse("-2 days").unwrap().to_si(), -2.0 * DAY);
+        assert_eq!(
+            Quantity::parse("1e3 M_earth").unwrap().unit,
+            Unit::EarthMass
+        );
+        for text in ["1.5", "AU", "1.5 parsec", "one AU", "1 AU extra", ""] {
+            assert!(Quantity::parse(text).is_err(), "'{}' was parsed", text);
+        }
+    }
+
+    #[test]
+    fn every_symbol_reads_back() {
+        for symbol in [
+            "m", "km", "AU", "kg", "Mearth", "Msun", "s", "d", "yr", "m/s", "km/s",
+        ] {
+            assert_eq!(Unit::from_symbol(symbol).unwrap().symbol(), symbol);
+        }
+    }
+
+    #[test]
+    fn solar_units_give_a_one_year_orbit() {
+        let units = UnitSystem::solar(GRAV_CONST, UnitStyle::Astronomical);
+        assert!(close(
+            units.to_sim(ASTRONOMICAL_UNIT, Dimension::Length),
+            200.0,
+            1e-12
+        ));
+        assert!(close(
+            units.to_sim(SOLAR_MASS, Dimension::Mass),
+            7000.0,
+            1e-12
+        ));
+        // Kepler's third law for a body at 1 AU around one solar mass
+        let period =
+            2.0 * std::f64::consts::PI * (200.0_f64.powi(3) / (GRAV_CONST * 7000.0)).sqrt();
+        assert!(close(units.to_si(period, Dimension::Time), YEAR, 1e-3));
+        // The simulation's G is the real one in these units
+        let g_si = GRAV_CONST * units.length.powi(3) / (units.mass * units.time.powi(2));
+        assert!(close(g_si, G_SI, 1e-12));
+    }
+
+    #[test]
+    fn conversions_round_trip() {
+        let units = UnitSystem::new(1e9, 1e27, GRAV_CONST, UnitStyle::Si);
+        for dimension in [
+            Dimension::Length,
+            Dimension::Mass,
+            Dimension::Time,
+            Dimension::Velocity,
+            Dimension::Acceleration,
+            Dimension::Energy,
+        ] {
+            let sim = units.to_sim(12.5, dimension);
+            assert!(close(units.to_si(sim, dimension), 12.5, 1e-12));
+        }
+        let speed = units.to_si(1.0, Dimension::Length) / units.to_si(1.0, Dimension::Time);
+        assert!(close(units.to_si(1.0, Dimension::Velocity), speed, 1e-12));
+    }
+
+    #[test]
+    fn parse_and_show_in_simulation_units() {
+        let units = UnitSystem::solar(GRAV_CONST, UnitStyle::Astronomical);
+        assert!(close(
+            units.parse("2 AU", Dimension::Length).unwrap(),
+            400.0,
+            1e-12
+        ));
+        assert!(units.parse("2 AU", Dimension::Mass).is_err());
+        assert_eq!(units.show(300.0, Dimension::Length), "1.500 AU");
+        assert_eq!(units.show(3500.0, Dimension::Mass), "0.500 Msun");
+        assert_eq!(show(None, 2.0, Dimension::Length), "2.00");
+        let si = UnitSystem::solar(GRAV_CONST, UnitStyle::Si);
+        assert_eq!(si.show(200.0, Dimension::Length), "1.496e11 m");
+    }
+}
@@ -0,0 +1,411 @@
+use super::events::{EventBus, EventDetector, SimEvent};
+use super::field::Probe;
+use super::observer::{Notice, WorldObserver};
+use super::settings::{Integrator, SimSettings};
+use super::snapshot::Snapshot;
+use super::tidal;
+use crate::celestial_bodies::body_config::CelestialBody;
+use crate::celestial_bodies::planet::Planet;
+use crate::utils::array_logic::{add_arrays, scalar_mult};
+use crate::utils::physics::{grav_force, grav_potential, pn_correction};
+use rand_chacha::ChaCha8Rng;
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+use std::mem;
+use std::rc::Rc;
+
+/// Random number generator owned by the world, its state
+/// can be saved so a run continues exactly where it left off
+pub type SimRng = ChaCha8Rng;
+
+/// Length of one step when not driven by a window,
+/// the same as piston's default of 120 updates per second
+pub const FIXED_DT: f64 = 1.0 / 120.0;
+
+/// Running totals of what happened to the bodies, kept
+/// for statistics but not part of the saved state
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EventCounts {
+    /// Bodies that crossed the boundary and were put back at random
+    pub ejections: u64,
+    /// Pairs of bodies that merged
+    pub collisions: u64,
+    /// Bodies torn apart within a Roche limit
+    pub disruptions: u64,
+    /// Ids of the bodies that were ejected at least once
+    pub ejected: BTreeSet<u32>,
+}
+
+impl EventCounts {
+    fn eject(&mut self, id: u32) {
+        self.ejections += 1;
+        self.ejected.insert(id);
+    }
+}
+
+/// A force acting on the bodies alongside gravity, e.g. one written
+/// in a script. It is gathered along with gravity before every step
+pub trait ExternalForce {
+    /// Force on each body, in the same order as the bodies
+    fn forces(&mut self, planets: &[Planet], time: f64) -> Vec<[f64; 2]>;
+}
+
+/// An external force that copies of the world, e.g. a prediction, act under as well
+pub type SharedForce = Rc<RefCell<dyn ExternalForce>>;
+
+/// The world holds every body in the simulation
+/// along with the settings used to step it forward
+pub struct World {
+    pub planets: Vec<Planet>,
+    pub settings: SimSettings,
+    pub centre: [f64; 2],
+    pub time: f64,
+    /// Number of steps taken so far
+    pub steps: u64,
+    pub rng: SimRng,
+    pub counts: EventCounts,
+    /// Events of the steps so far, for the GUI or a log to take
+    pub events: EventBus,
+    pub detector: EventDetector,
+    /// Forces besides gravity, which are not part of the saved state
+    pub forces: Vec<SharedForce>,
+    /// Told of every step and of changes to the bodies, not part of the saved state
+    pub observers: Vec<Box<dyn WorldObserver>>,
+    /// Changes made during the step, for the observers at its end
+    notices: Vec<Notice>,
+    stepping: bool,
+    /// Total energy that the drift is measured against,
+    /// taken again whenever bodies are reset or edited
+    energy_baseline: Option<f64>,
+}
+
+impl World {
+    pub fn new(
+        planets: Vec<Planet>,
+        centre: [f64; 2],
+        settings: SimSettings,
+        rng: SimRng,
+    ) -> World {
+        World {
+            planets,
+            settings,
+            centre,
+            time: 0.0,
+            steps: 0,
+            rng,
+            counts: EventCounts::default(),
+            events: EventBus::new(),
+            detector: EventDetector::new(),
+            forces: Vec::new(),
+            observers: Vec::new(),
+            notices: Vec::new(),
+            stepping: false,
+            energy_baseline: None,
+        }
+    }
+
+    /// Advance the simulation by one fixed step,
+    /// scaled by the time scale
+    pub fn advance(&mut self) {
+        self.step(FIXED_DT * self.settings.time_scale);
+    }
+
+    /// Advance the simulation by dt using the selected integrator,
+    /// the forces for the next step are gathered along the way
+    pub fn step(&mut self, dt: f64) {
+        self.call_observers(|observer, world| observer.step_begin(world));
+        self.stepping = true;
+        let ejections = self.counts.ejections;
+        match self.settings.integrator {
+            Integrator::SymplecticEuler => {
+                // Handle step-wise update of each planet
+                for i in 0..self.planets.len() {
+                    self.planets[i].update(dt); // pass 'dt' value to scale movement
+                    self.check_boundary(i, dt);
+                }
+                self.apply_forces();
+            }
+            Integrator::VelocityVerlet => {
+                for i in 0..self.planets.len() {
+                    let planet = &mut self.planets[i];
+                    planet.kick(0.5 * dt);
+                    planet.drift(dt);
+                    planet.clear_acceleration();
+                    self.check_boundary(i, dt);
+                }
+                self.apply_forces();
+                for planet in self.planets.iter_mut() {
+                    planet.kick(0.5 * dt);
+                }
+            }
+        }
+        self.time += dt;
+        self.steps += 1;
+
+        let mut changed = false;
+        if self.settings.tidal_disruption {
+            let disrupted = tidal::disrupt_bodies(self);
+            self.counts.disruptions += disrupted as u64;
+            changed |= disrupted > 0;
+        }
+        if self.settings.collisions {
+            let merged = tidal::merge_collisions(self);
+            self.counts.collisions += merged as u64;
+            changed |= merged > 0;
+        }
+        if changed {
+            // Bodies were added or removed, so the forces gathered
+            // for the next step no longer match them
+            self.refresh_forces();
+        }
+
+        let primary = self.heaviest();
+        let g = self.settings.grav_const;
+        self.detector
+            .observe(&self.planets, primary, g, self.time, &mut self.events);
+
+        // Collisions and disruptions do not conserve energy,
+        // so the drift is measured from after them
+        let reset = self.counts.ejections != ejections;
+        if reset || changed || self.energy_baseline.is_none() {
+            self.rebase_energy();
+        }
+
+        self.stepping = false;
+        self.tell_observers();
+        self.call_observers(|observer, world| observer.step_end(world));
+    }
+
+    /// Put a body that crossed the boundary back at random
+    fn check_boundary(&mut self, index: usize, dt: f64) {
+        let planet = &mut self.planets[index];
+        // The body as it left is only kept for the observers
+        let left = (!self.observers.is_empty()).then(|| planet.clone());
+        if !planet.check_dist_from_centre(self.centre, &mut self.rng) {
+            return;
+        }
+        let id = planet.id();
+        self.counts.eject(id);
+        self.detector.forget(id);
+        self.events.emit(SimEvent::BoundaryReset {
+            time: self.time + dt,
+            id,
+        });
+        if let Some(left) = left {
+            self.notice(|_| Notice::Boundary(left));
+            self.notice(|world| Notice::Reset(world.planets[index].clone()));
+        }
+    }
+
+    /// Add a body to the world
+    pub fn spawn(&mut self, planet: Planet) {
+        self.planets.push(planet);
+        self.notice(|world| Notice::Spawned(world.planets[world.planets.len() - 1].clone()));
+    }
+
+    /// Take the body at the index out of the world
+    pub fn remove(&mut self, index: usize) -> Planet {
+        let planet = self.planets.remove(index);
+        self.detector.forget(planet.id());
+        self.notice(|_| Notice::Removed(planet.clone()));
+        planet
+    }
+
+    /// Let the observers know of a change to the bodies, at the
+    /// end of the step when it was made during one. The notice is
+    /// only made when there are observers, as it holds copies of bodies
+    pub fn notice(&mut self, notice: impl FnOnce(&World) -> Notice) {
+        if self.observers.is_empty() {
+            return;
+        }
+        let notice = notice(self);
+        self.notices.push(notice);
+        if !self.stepping {
+            self.tell_observers();
+        }
+    }
+
+    fn tell_observers(&mut self) {
+        let notices = mem::take(&mut self.notices);
+        self.call_observers(|observer, world| {
+            for notice in notices.iter() {
+                notice.tell(observer, world);
+            }
+        });
+    }
+
+    /// Run the callback for every observer. They are taken out of the
+    /// world for the time, so that they can be given the world itself
+    fn call_observers(&mut self, mut callback: impl FnMut(&mut dyn WorldObserver, &World)) {
+        if self.observers.is_empty() {
+            return;
+        }
+        let mut observers = mem::take(&mut self.observers);
+        for observer in observers.iter_mut() {
+            callback(observer.as_mut(), self);
+        }
+        self.observers = observers;
+    }
+
+    /// Copy of the current state to look ahead with, e.g. for a prediction.
+    /// It acts under the same forces, but none of the observers are told of it
+    pub fn fork(&self) -> World {
+        let mut fork = Snapshot::capture(self).restore();
+        fork.forces = self.forces.clone();
+        fork
+    }
+
+    /// Continue from another state of the world, e.g. a snapshot,
+    /// keeping the forces and observers attached to this one
+    pub fn replace(&mut self, mut next: World) {
+        next.forces = mem::take(&mut self.forces);
+        next.observers = mem::take(&mut self.observers);
+        *self = next;
+    }
+
+    /// Gather the forces for the next step anew, after
+    /// bodies were moved outside of a step
+    pub fn refresh_forces(&mut self) {
+        for planet in self.planets.iter_mut() {
+            planet.clear_acceleration();
+        }
+        self.apply_forces();
+    }
+
+    /// Handle gravitational force for unique planet pair
+    /// e.g. for 5 planets we have 4+3+2+1=10 force calcs,
+    /// then add the external forces
+    fn apply_forces(&mut self) {
+        let g = self.settings.grav_const;
+        for i in 0..self.planets.len() {
+            for j in (i + 1)..self.planets.len() {
+                // Obtain force, it is always equal and opposite,
+                // the .add_force method scales the force by the mass
+                // of the body
+                let (force, force_inv) = grav_force(&self.planets[i], &self.planets[j], g);
+                self.planets[i].add_force(force_inv);
+                self.planets[j].add_force(force);
+
+                if let Some(c) = self.settings.speed_of_light {
+                    let (force, force_inv) =
+                        pn_correction(&self.planets[i], &self.planets[j], g, c);
+                    self.planets[i].add_force(force_inv);
+                    self.planets[j].add_force(force);
+                }
+            }
+        }
+        for field in self.forces.iter_mut() {
+            let forces = field.borrow_mut().forces(&self.planets, self.time);
+            for (planet, force) in self.planets.iter_mut().zip(forces) {
+                planet.add_force(force);
+            }
+        }
+    }
+
+    /// Acceleration a test particle at rest at `pos` would feel from
+    /// gravity, including the 1PN term when it is enabled. External
+    /// forces are left out, they are only defined on the bodies
+    pub fn acceleration_at(&self, pos: [f64; 2]) -> [f64; 2] {
+        let g = self.settings.grav_const;
+        let probe = Probe::at(pos);
+        let mut force = [0.0, 0.0];
+        for planet in self.planets.iter() {
+            // The force on the probe is the one acting on mass1
+            let (_, on_probe) = grav_force(&probe, planet, g);
+            force = add_arrays(force, on_probe);
+            if let Some(c) = self.settings.speed_of_light {
+                let (_, on_probe) = pn_correction(&probe, planet, g, c);
+                force = add_arrays(force, on_probe);
+            }
+        }
+        scalar_mult(force, 1.0 / probe.mass())
+    }
+
+    /// Newtonian gravitational potential (energy per unit mass) at `pos`.
+    /// The 1PN term depends on velocities and external forces on the
+    /// bodies, so neither has a potential to add here
+    pub fn potential_at(&self, pos: [f64; 2]) -> f64 {
+        let g = self.settings.grav_const;
+        let probe = Probe::at(pos);
+        let energy: f64 = self
+            .planets
+            .iter()
+            .map(|planet| grav_potential(&probe, planet, g))
+            .sum();
+        energy / probe.mass()
+    }
+
+    /// An id that no body in the world has yet
+    pub fn next_id(&self) -> u32 {
+        self.planets
+            .iter()
+            .map(|planet| planet.id() + 1)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Index of the body with the id, if it is still in the world
+    pub fn index_of(&self, id: u32) -> Option<usize> {
+        self.planets.iter().position(|planet| planet.id() == id)
+    }
+
+    /// Index of the heaviest body, which acts as
+    /// the primary for orbit diagnostics
+    pub fn heaviest(&self) -> Option<usize> {
+        (0..self.planets.len())
+            .max_by(|&a, &b| self.planets[a].mass().total_cmp(&self.planets[b].mass()))
+    }
+
+    /// Total kinetic and potential energy of all bodies
+    pub fn total_energy(&self) -> f64 {
+        let g = self.settings.grav_const;
+        let kinetic: f64 = self
+            .planets
+            .iter()
+            .map(|planet| planet.kinetic_energy())
+            .sum();
+        let mut potential = 0.0;
+        for i in 0..self.planets.len() {
+            for j in (i + 1)..self.planets.len() {
+                potential += grav_potential(&self.planets[i], &self.planets[j], g);
+            }
+        }
+        kinetic + potential
+    }
+
+    /// Relative change of the total energy since the baseline,
+    /// a measure of the error made by the integrator
+    pub fn energy_drift(&self) -> f64 {
+        match self.energy_baseline {
+            Some(baseline) if baseline != 0.0 => (self.total_energy() - baseline) / baseline.abs(),
+            _ => 0.0,
+        }
+    }
+
+    /// Measure the energy drift from the current state onwards,
+    /// needed after bodies were changed by hand
+    pub fn rebase_energy(&mut self) {
+        self.energy_baseline = Some(self.total_energy());
+    }
+
+    /// Hash of the exact state of every body, two worlds
+    /// with the same fingerprint have followed the same path
+    pub fn fingerprint(&self) -> u64 {
+        // FNV-1a over the bits of each value, stable across runs and platforms
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        let mut feed = |value: f64| {
+            for byte in value.to_bits().to_le_bytes() {
+                hash ^= byte as u64;
+                hash = hash.wrapping_mul(0x0100_0000_01b3);
+            }
+        };
+        feed(self.time);
+        for planet in self.planets.iter() {
+            let (pos, vel) = (planet.pos(), planet.vel());
+            for value in [pos[0], pos[1], vel[0], vel[1], planet.mass()] {
+                feed(value);
+            }
+        }
+        hash
+    }
+}
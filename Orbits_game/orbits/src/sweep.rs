@@ -0,0 +1,410 @@
+use crate::celestial_bodies::body_config::PlanetConfig;
+use crate::cli::Options;
+use crate::simulation::world::World;
+use std::collections::BTreeSet;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+/// Group of generated bodies whose configuration a parameter changes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigGroup {
+    /// The small planets
+    Planets,
+    /// The heavy body they orbit
+    Primary,
+}
+
+/// A field of `PlanetConfig` that can be swept over
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigField {
+    LowerPosBound,
+    UpperPosBound,
+    VelocityBound,
+    MassMean,
+    MassStd,
+    Density,
+}
+
+impl ConfigField {
+    pub fn from_name(name: &str) -> Option<ConfigField> {
+        let field = match name {
+            "lower_pos_bound" => ConfigField::LowerPosBound,
+            "upper_pos_bound" => ConfigField::UpperPosBound,
+            "velocity_bound" => ConfigField::VelocityBound,
+            "mass_mean" => ConfigField::MassMean,
+            "mass_std" => ConfigField::MassStd,
+            "density" => ConfigField::Density,
+            _ => return None,
+        };
+        Some(field)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ConfigField::LowerPosBound => "lower_pos_bound",
+            ConfigField::UpperPosBound => "upper_pos_bound",
+            ConfigField::VelocityBound => "velocity_bound",
+            ConfigField::MassMean => "mass_mean",
+            ConfigField::MassStd => "mass_std",
+            ConfigField::Density => "density",
+        }
+    }
+
+    fn set(&self, config: &mut PlanetConfig, value: f64) {
+        let field = match self {
+            ConfigField::LowerPosBound => &mut config.lower_pos_bound,
+            ConfigField::UpperPosBound => &mut config.upper_pos_bound,
+            ConfigField::VelocityBound => &mut config.velocity_bound,
+            ConfigField::MassMean => &mut config.mass_mean,
+            ConfigField::MassStd => &mut config.mass_std,
+            ConfigField::Density => &mut config.density,
+        };
+        *field = value;
+    }
+}
+
+/// One axis of the parameter grid, the values a single field takes
+#[derive(Debug, Clone, PartialEq)]
+pub struct SweepParameter {
+    pub group: ConfigGroup,
+    pub field: ConfigField,
+    pub values: Vec<f64>,
+}
+
+impl SweepParameter {
+    /// Parse e.g. "velocity_bound=10,50,100" or "primary.mass_mean=3500,7000",
+    /// fields without a group apply to the small planets
+    pub fn parse(text: &str) -> Result<SweepParameter, String> {
+        let (name, values) = text
+            .split_once('=')
+            .ok_or_else(|| format!("Expected FIELD=V1,V2,... in '{}'", text))?;
+        let (group, field) = match name.split_once('.') {
+            Some(("planets", field)) => (ConfigGroup::Planets, field),
+            Some(("primary", field)) => (ConfigGroup::Primary, field),
+            Some((group, _)) => {
+                return Err(format!(
+                    "Unknown group '{}', expected planets or primary",
+                    group
+                ))
+            }
+            None => (ConfigGroup::Planets, name),
+        };
+        let field =
+            ConfigField::from_name(field).ok_or_else(|| format!("Unknown field '{}'", field))?;
+        let values = values
+            .split(',')
+            .map(|value| {
+                value
+                    .trim()
+                    .parse()
+                    .map_err(|_| format!("Invalid number '{}' in '{}'", value, text))
+            })
+            .collect::<Result<Vec<f64>, String>>()?;
+        Ok(SweepParameter {
+            group,
+            field,
+            values,
+        })
+    }
+
+    /// Column name in the CSV, e.g. "primary.mass_mean"
+    pub fn name(&self) -> String {
+        match self.group {
+            ConfigGroup::Planets => format!("planets.{}", self.field.name()),
+            ConfigGroup::Primary => format!("primary.{}", self.field.name()),
+        }
+    }
+}
+
+/// A single point of the grid along with the seed to run it with
+#[derive(Debug, Clone, PartialEq)]
+pub struct SweepRun {
+    /// Value of each parameter, in the order they were given
+    pub values: Vec<f64>,
+    pub seed: u64,
+}
+
+impl SweepRun {
+    /// Apply the values of this run to the configurations of both groups,
+    /// fails when bodies can not be generated from the result
+    pub fn configure(
+        &self,
+        parameters: &[SweepParameter],
+        planets: &mut PlanetConfig,
+        primary: &mut PlanetConfig,
+    ) -> Result<(), String> {
+        for (parameter, &value) in parameters.iter().zip(self.values.iter()) {
+            let config = match parameter.group {
+                ConfigGroup::Planets => &mut *planets,
+                ConfigGroup::Primary => &mut *primary,
+            };
+            parameter.field.set(config, value);
+        }
+        planets
+            .validate()
+            .map_err(|err| format!("Invalid planets: {}", err))?;
+        primary
+            .validate()
+            .map_err(|err| format!("Invalid primary: {}", err))
+    }
+}
+
+/// Every combination of parameter values, each with every seed
+pub fn grid(parameters: &[SweepParameter], seeds: &[u64]) -> Vec<SweepRun> {
+    let mut points: Vec<Vec<f64>> = vec![Vec::new()];
+    for parameter in parameters {
+        points = points
+            .iter()
+            .flat_map(|point| {
+                parameter.values.iter().map(move |&value| {
+                    let mut point = point.clone();
+                    point.push(value);
+                    point
+                })
+            })
+            .collect();
+    }
+    points
+        .into_iter()
+        .flat_map(|values| {
+            seeds.iter().map(move |&seed| SweepRun {
+                values: values.clone(),
+                seed,
+            })
+        })
+        .collect()
+}
+
+/// Statistics of a finished run
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunSummary {
+    /// Bodies left at the end
+    pub bodies: usize,
+    /// Bodies of the start that are still there and never left the boundary
+    pub survivors: usize,
+    pub ejections: u64,
+    pub collisions: u64,
+    pub disruptions: u64,
+    /// Relative energy drift since the last ejection or collision
+    pub energy_drift: f64,
+}
+
+impl RunSummary {
+    fn measure(start: &BTreeSet<u32>, world: &World) -> RunSummary {
+        let survivors = world
+            .planets
+            .iter()
+            .filter(|planet| {
+                start.contains(&planet.id()) && !world.counts.ejected.contains(&planet.id())
+            })
+            .count();
+        RunSummary {
+            bodies: world.planets.len(),
+            survivors,
+            ejections: world.counts.ejections,
+            collisions: world.counts.collisions,
+            disruptions: world.counts.disruptions,
+            energy_drift: world.energy_drift(),
+        }
+    }
+}
+
+/// Run every point of the grid for the number of steps in the options,
+/// spread over worker threads, and write a summary of each run to the
+/// CSV file. `build` creates the world of a run, a run it fails
+/// for is written as a row with the error instead
+pub fn run<F>(options: &Options, build: F)
+where
+    F: Fn(&SweepRun) -> Result<World, String> + Sync,
+{
+    let seeds = match (&options.seeds, options.seed) {
+        (seeds, _) if !seeds.is_empty() => seeds.clone(),
+        (_, Some(seed)) => vec![seed],
+        (_, None) => vec![0],
+    };
+    let runs = grid(&options.sweep, &seeds);
+    let threads = match options.threads {
+        0 => thread::available_parallelism().map_or(1, |n| n.get()),
+        threads => threads,
+    }
+    .min(runs.len())
+    .max(1);
+    println!(
+        "Sweeping {} runs of {} steps on {} threads",
+        runs.len(),
+        options.steps,
+        threads
+    );
+
+    // Workers take the next run until none are left
+    let next = AtomicUsize::new(0);
+    let summaries = Mutex::new(vec![None; runs.len()]);
+    thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let run = match runs.get(index) {
+                    Some(run) => run,
+                    None => break,
+                };
+                let summary = build(run).map(|mut world| {
+                    // The counts are all that is needed, not the events
+                    world.detector.enabled = false;
+                    let start: BTreeSet<u32> =
+                        world.planets.iter().map(|planet| planet.id()).collect();
+                    for _ in 0..options.steps {
+                        world.advance();
+                    }
+                    RunSummary::measure(&start, &world)
+                });
+                summaries.lock().unwrap()[index] = Some(summary);
+            });
+        }
+    });
+
+    let summaries: Vec<Result<RunSummary, String>> = summaries
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .flatten()
+        .collect();
+    let failed = summaries.iter().filter(|summary| summary.is_err()).count();
+    if failed > 0 {
+        eprintln!("{} of {} runs could not be set up", failed, runs.len());
+    }
+    match write_csv(
+        &options.sweep_csv,
+        &options.sweep,
+        options.steps,
+        &runs,
+        &summaries,
+    ) {
+        Ok(()) => println!(
+            "Wrote {} runs to {}",
+            summaries.len(),
+            options.sweep_csv.display()
+        ),
+        Err(err) => eprintln!("Could not write {}: {}", options.sweep_csv.display(), err),
+    }
+}
+
+fn write_csv(
+    path: &Path,
+    parameters: &[SweepParameter],
+    steps: u64,
+    runs: &[SweepRun],
+    summaries: &[Result<RunSummary, String>],
+) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    let names: Vec<String> = parameters.iter().map(SweepParameter::name).collect();
+    write!(out, "run,seed,")?;
+    for name in names.iter() {
+        write!(out, "{},", name)?;
+    }
+    writeln!(
+        out,
+        "steps,bodies,survivors,ejections,collisions,disruptions,energy_drift,error"
+    )?;
+    for (i, (run, summary)) in runs.iter().zip(summaries.iter()).enumerate() {
+        write!(out, "{},{},", i, run.seed)?;
+        for value in run.values.iter() {
+            write!(out, "{},", value)?;
+        }
+        match summary {
+            Ok(summary) => writeln!(
+                out,
+                "{},{},{},{},{},{},{:e},",
+                steps,
+                summary.bodies,
+                summary.survivors,
+                summary.ejections,
+                summary.collisions,
+                summary.disruptions,
+                summary.energy_drift
+            )?,
+            // The error is quoted, it may hold commas
+            Err(err) => writeln!(out, "{},,,,,,,\"{}\"", steps, err.replace('"', "\"\""))?,
+        }
+    }
+    out.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> PlanetConfig {
+        PlanetConfig::new(0.0, 1000.0, 50.0, 10.0, 5.0, 1.0)
+    }
+
+    #[test]
+    fn parse_parameters() {
+        let parameter = SweepParameter::parse("primary.mass_mean=3500, 7000").unwrap();
+        assert_eq!(parameter.group, ConfigGroup::Primary);
+        assert_eq!(parameter.field, ConfigField::MassMean);
+        assert_eq!(parameter.values, vec![3500.0, 7000.0]);
+        assert_eq!(parameter.name(), "primary.mass_mean");
+        let parameter = SweepParameter::parse("velocity_bound=10").unwrap();
+        assert_eq!(parameter.group, ConfigGroup::Planets);
+        for text in [
+            "velocity_bound",
+            "moons.density=1",
+            "speed=1",
+            "density=1,x",
+        ] {
+            assert!(
+                SweepParameter::parse(text).is_err(),
+                "'{}' was parsed",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn grid_covers_every_combination_and_seed() {
+        let parameters = [
+            SweepParameter::parse("velocity_bound=10,20,30").unwrap(),
+            SweepParameter::parse("primary.density=1,2").unwrap(),
+        ];
+        let runs = grid(&parameters, &[7, 8]);
+        assert_eq!(runs.len(), 12);
+        assert_eq!(
+            runs[0],
+            SweepRun {
+                values: vec![10.0, 1.0],
+                seed: 7
+            }
+        );
+        assert_eq!(runs[1].seed, 8);
+        assert_eq!(runs[11].values, vec![30.0, 2.0]);
+        // Without parameters there is one run per seed
+        assert_eq!(grid(&[], &[1, 2, 3]).len(), 3);
+    }
+
+    #[test]
+    fn configure_refuses_configs_bodies_can_not_be_drawn_from() {
+        let parameters = [
+            SweepParameter::parse("velocity_bound=0,20").unwrap(),
+            SweepParameter::parse("primary.mass_std=-1,2").unwrap(),
+            SweepParameter::parse("lower_pos_bound=0,1000").unwrap(),
+        ];
+        let valid: Vec<bool> = grid(&parameters, &[0])
+            .iter()
+            .map(|run| {
+                let (mut planets, mut primary) = (config(), config());
+                let result = run.configure(&parameters, &mut planets, &mut primary);
+                if result.is_ok() {
+                    assert_eq!(planets.velocity_bound, run.values[0]);
+                    assert_eq!(primary.mass_std, run.values[1]);
+                }
+                result.is_ok()
+            })
+            .collect();
+        assert_eq!(valid.iter().filter(|&&valid| valid).count(), 1);
+        assert!(valid[6]);
+    }
+}
@@ -0,0 +1,73 @@
+use super::text;
+use crate::render::renderer::Renderer;
+use crate::simulation::units::{self, Dimension};
+use crate::simulation::world::World;
+use crate::utils::colour::Colour;
+use std::time::Instant;
+
+const TEXT: Colour = [0.6, 1.0, 0.6, 1.0];
+
+/// Heads-up display with frame rate and
+/// the state of the simulation
+pub struct Hud {
+    pub visible: bool,
+    frames: u32,
+    fps: f64,
+    last_sample: Instant,
+}
+
+impl Hud {
+    pub fn new() -> Hud {
+        Hud {
+            visible: true,
+            frames: 0,
+            fps: 0.0,
+            last_sample: Instant::now(),
+        }
+    }
+
+    /// Count a rendered frame, the frame rate
+    /// is sampled about once per second
+    pub fn frame(&mut self) {
+        self.frames += 1;
+        let elapsed = self.last_sample.elapsed().as_secs_f64();
+        if elapsed >= 1.0 {
+            self.fps = self.frames as f64 / elapsed;
+            self.frames = 0;
+            self.last_sample = Instant::now();
+        }
+    }
+
+    pub fn lines(&self, world: &World) -> Vec<String> {
+        vec![
+            format!("fps          {:.0}", self.fps),
+            format!(
+                "time         {}",
+                units::show(world.settings.units.as_ref(), world.time, Dimension::Time)
+            ),
+            format!("bodies       {}", world.planets.len()),
+            format!("time scale   x{}", world.settings.time_scale),
+            format!("integrator   {}", world.settings.integrator.name()),
+            format!("energy drift {:+.3e}", world.energy_drift()),
+            format!("tides        {}", on_off(world.settings.tidal_disruption)),
+            format!("collisions   {}", on_off(world.settings.collisions)),
+        ]
+    }
+
+    /// Draw the HUD at the given position when visible,
+    /// returns the height it takes up
+    pub fn draw<R: Renderer>(&self, world: &World, pos: [f64; 2], renderer: &mut R) -> f64 {
+        if !self.visible {
+            return 0.0;
+        }
+        text::draw_panel(&self.lines(world), pos, TEXT, renderer)
+    }
+}
+
+fn on_off(enabled: bool) -> &'static str {
+    if enabled {
+        "on"
+    } else {
+        "off"
+    }
+}
@@ -0,0 +1,318 @@
+use super::text;
+use crate::celestial_bodies::body_config::CelestialBody;
+use crate::render::renderer::Renderer;
+use crate::simulation::action::Action;
+use crate::simulation::lagrange::PairGeometry;
+use crate::simulation::orbit::OrbitalElements;
+use crate::simulation::units::{self, Dimension};
+use crate::simulation::world::World;
+use crate::utils::array_logic::Length;
+use crate::utils::colour::Colour;
+use piston::input::Key;
+use std::f64::consts::PI;
+
+const TEXT: Colour = [1.0; 4];
+const GHOST: Colour = [1.0, 1.0, 1.0, 0.3];
+const LAGRANGE: Colour = [0.4, 0.9, 1.0, 0.9];
+const HILL: Colour = [0.4, 0.9, 1.0, 0.4];
+const ROCHE: Colour = [1.0, 0.4, 0.3, 0.5];
+const CONIC_SEGMENTS: usize = 180;
+const CIRCLE_SEGMENTS: usize = 64;
+const MARKER_SIZE: f64 = 4.0;
+// Extra pixels around a body that still count as clicking it
+const PICK_SLACK: f64 = 4.0;
+// Change to the mass (factor) and velocity (px/s) per key press
+const MASS_STEP: f64 = 1.1;
+const VELOCITY_STEP: f64 = 5.0;
+// Hyperbolae are cut off once they are this far from the primary
+const MAX_CONIC_RADIUS: f64 = 5000.0;
+
+/// Keeps track of the selected body and the primary its orbit is
+/// described around, by id so they stay put when bodies come and go
+pub struct Inspector {
+    pub selected: Option<u32>,
+    /// When unset, the heaviest body in the world is used
+    pub primary: Option<u32>,
+}
+
+impl Inspector {
+    pub fn new() -> Inspector {
+        Inspector {
+            selected: None,
+            primary: None,
+        }
+    }
+
+    /// Move the selection on to the next body,
+    /// wrapping back to no selection at the end
+    pub fn select_next(&mut self, world: &World) {
+        self.selected = next_id(self.selected, world);
+    }
+
+    /// Move the primary on to the next body, wrapping
+    /// back to the heaviest body at the end
+    pub fn primary_next(&mut self, world: &World) {
+        self.primary = next_id(self.primary, world);
+    }
+
+    /// Select the body under the given point, clears the
+    /// selection when clicking on empty space
+    pub fn select_at(&mut self, world: &World, point: [f64; 2]) {
+        // Bodies drawn last end up on top, so prefer those
+        self.selected = world
+            .planets
+            .iter()
+            .rfind(|planet| planet.contains_point(point, PICK_SLACK))
+            .map(|planet| planet.id());
+    }
+
+    /// Live-edit the selected body, returns the edit
+    /// to apply when the key is used for editing
+    pub fn handle_key(&self, key: Key, world: &World) -> Option<Action> {
+        let index = world.index_of(self.selected?)?;
+        let planet = &world.planets[index];
+        let vel = planet.vel();
+        let velocity = match key {
+            Key::Equals => {
+                let mass = planet.mass() * MASS_STEP;
+                return Some(Action::SetMass { index, mass });
+            }
+            Key::Minus => {
+                let mass = planet.mass() / MASS_STEP;
+                return Some(Action::SetMass { index, mass });
+            }
+            Key::Delete => return Some(Action::Remove { index }),
+            Key::Up => [vel[0], vel[1] - VELOCITY_STEP],
+            Key::Down => [vel[0], vel[1] + VELOCITY_STEP],
+            Key::Left => [vel[0] - VELOCITY_STEP, vel[1]],
+            Key::Right => [vel[0] + VELOCITY_STEP, vel[1]],
+            Key::Z => [0.0, 0.0],
+            _ => return None,
+        };
+        Some(Action::SetVelocity { index, velocity })
+    }
+
+    /// Indices of the selected body and its primary,
+    /// when both exist and they differ
+    pub fn pair(&self, world: &World) -> Option<(usize, usize)> {
+        let selected = world.index_of(self.selected?)?;
+        let primary = match self.primary {
+            Some(id) => world.index_of(id)?,
+            None => world.heaviest()?,
+        };
+        if selected == primary {
+            return None;
+        }
+        Some((selected, primary))
+    }
+
+    /// Osculating elements of the selected body around the primary
+    pub fn elements(&self, world: &World) -> Option<(usize, usize, OrbitalElements)> {
+        let (selected, primary) = self.pair(world)?;
+        let (body, primary_body) = (&world.planets[selected], &world.planets[primary]);
+        let elements = OrbitalElements::between(body, primary_body, world.settings.grav_const);
+        Some((selected, primary, elements))
+    }
+
+    /// Lagrange points, Hill sphere and Roche limit of the selected body around the primary
+    pub fn geometry(&self, world: &World) -> Option<PairGeometry> {
+        let (selected, primary) = self.pair(world)?;
+        PairGeometry::between(&world.planets[primary], &world.planets[selected])
+    }
+
+    /// Text lines describing the state and orbit of the selected body
+    pub fn lines(&self, world: &World) -> Vec<String> {
+        let selected = match self.selected.and_then(|id| world.index_of(id)) {
+            Some(selected) => selected,
+            None => return Vec::new(),
+        };
+        let planet = &world.planets[selected];
+        let (pos, vel, acc) = (planet.pos(), planet.vel(), planet.acceleration());
+        let show = |value, dimension| units::show(world.settings.units.as_ref(), value, dimension);
+        let mut lines = vec![
+            format!("body {} (id {})", selected, planet.id()),
+            format!("mass   = {}", show(planet.mass(), Dimension::Mass)),
+            format!(
+                "radius = {} (density {:.2})",
+                show(planet.radius(), Dimension::Length),
+                planet.density()
+            ),
+            format!("pos    = ({:.1}, {:.1})", pos[0], pos[1]),
+            format!("vel    = ({:.2}, {:.2})", vel[0], vel[1]),
+            format!("speed  = {}", show(vel.get_length(), Dimension::Velocity)),
+            format!(
+                "acc    = {}",
+                show(acc.get_length(), Dimension::Acceleration)
+            ),
+            format!(
+                "KE     = {}",
+                show(planet.kinetic_energy(), Dimension::Energy)
+            ),
+        ];
+        lines.extend(self.orbit_lines(world));
+        lines.extend(self.geometry_lines(world));
+        lines.push("[-/=] mass  [arrows] vel  [z] stop  [del] remove".to_string());
+        lines
+    }
+
+    /// Text lines describing the orbit of the selected body
+    fn orbit_lines(&self, world: &World) -> Vec<String> {
+        let (_, primary, elements) = match self.elements(world) {
+            Some(found) => found,
+            None => return Vec::new(),
+        };
+        let show = |value, dimension| units::show(world.settings.units.as_ref(), value, dimension);
+        let mut lines = vec![
+            format!("orbit around #{}", world.planets[primary].id()),
+            format!(
+                "a      = {}",
+                show(elements.semi_major_axis, Dimension::Length)
+            ),
+            format!("e      = {:.4}", elements.eccentricity),
+            format!("omega  = {:.1} deg", elements.arg_periapsis.to_degrees()),
+            format!("nu     = {:.1} deg", elements.true_anomaly.to_degrees()),
+            format!("q      = {}", show(elements.periapsis(), Dimension::Length)),
+        ];
+        match elements.period() {
+            Some(period) => {
+                if let Some(apoapsis) = elements.apoapsis() {
+                    lines.push(format!("Q      = {}", show(apoapsis, Dimension::Length)));
+                }
+                lines.push(format!("period = {}", show(period, Dimension::Time)));
+                lines.push("bound".to_string());
+            }
+            None => lines.push("unbound (escaping)".to_string()),
+        }
+        lines
+    }
+
+    /// Text lines with the Hill sphere and Roche limit of the selected body
+    fn geometry_lines(&self, world: &World) -> Vec<String> {
+        let geometry = match self.geometry(world) {
+            Some(geometry) => geometry,
+            None => return Vec::new(),
+        };
+        let show = |value| units::show(world.settings.units.as_ref(), value, Dimension::Length);
+        let mut lines = vec![
+            format!("hill r = {}", show(geometry.hill_radius)),
+            format!("roche  = {}", show(geometry.roche_limit)),
+        ];
+        if geometry.within_roche_limit() {
+            lines.push("within the Roche limit".to_string());
+        }
+        lines
+    }
+
+    /// Draw the body panel at the given position along with the
+    /// osculating conic, Lagrange points, Hill sphere and Roche limit
+    pub fn draw<R: Renderer>(&self, world: &World, pos: [f64; 2], renderer: &mut R) {
+        if let Some((_, primary, elements)) = self.elements(world) {
+            draw_conic(&elements, world.planets[primary].pos(), renderer);
+        }
+        if let (Some(geometry), Some((selected, primary))) =
+            (self.geometry(world), self.pair(world))
+        {
+            draw_geometry(
+                &geometry,
+                world.planets[primary].pos(),
+                world.planets[selected].pos(),
+                renderer,
+            );
+        }
+        text::draw_panel(&self.lines(world), pos, TEXT, renderer);
+    }
+}
+
+/// Id of the body after the current one, or of the first body when
+/// the current one is gone. None after the last body
+fn next_id(current: Option<u32>, world: &World) -> Option<u32> {
+    let next = match current.and_then(|id| world.index_of(id)) {
+        None => 0,
+        Some(i) => i + 1,
+    };
+    world.planets.get(next).map(|planet| planet.id())
+}
+
+/// Draw the Lagrange points as labelled crosses, the Hill sphere
+/// around the secondary and the Roche limit around the primary
+fn draw_geometry<R: Renderer>(
+    geometry: &PairGeometry,
+    primary: [f64; 2],
+    secondary: [f64; 2],
+    renderer: &mut R,
+) {
+    draw_circle_outline(HILL, secondary, geometry.hill_radius, renderer);
+    draw_circle_outline(ROCHE, primary, geometry.roche_limit, renderer);
+    for (i, point) in geometry.lagrange.iter().enumerate() {
+        let [x, y] = *point;
+        renderer.line(
+            LAGRANGE,
+            0.5,
+            [
+                x - MARKER_SIZE,
+                y - MARKER_SIZE,
+                x + MARKER_SIZE,
+                y + MARKER_SIZE,
+            ],
+        );
+        renderer.line(
+            LAGRANGE,
+            0.5,
+            [
+                x - MARKER_SIZE,
+                y + MARKER_SIZE,
+                x + MARKER_SIZE,
+                y - MARKER_SIZE,
+            ],
+        );
+        renderer.text(
+            LAGRANGE,
+            11,
+            [x + MARKER_SIZE + 2.0, y - MARKER_SIZE],
+            &format!("L{}", i + 1),
+        );
+    }
+}
+
+fn draw_circle_outline<R: Renderer>(
+    colour: Colour,
+    centre: [f64; 2],
+    radius: f64,
+    renderer: &mut R,
+) {
+    let point = |i: usize| {
+        let angle = 2.0 * PI * i as f64 / CIRCLE_SEGMENTS as f64;
+        [
+            centre[0] + radius * angle.cos(),
+            centre[1] + radius * angle.sin(),
+        ]
+    };
+    for i in 0..CIRCLE_SEGMENTS {
+        let (from, to) = (point(i), point(i + 1));
+        renderer.line(colour, 0.5, [from[0], from[1], to[0], to[1]]);
+    }
+}
+
+/// Draw the conic of an orbit as a ghost line,
+/// `focus` being the position of the primary
+fn draw_conic<R: Renderer>(elements: &OrbitalElements, focus: [f64; 2], renderer: &mut R) {
+    // A hyperbola only spans the true anomalies where 1 + e cos(v) > 0
+    let max_anomaly = if elements.is_bound() {
+        PI
+    } else {
+        (-1.0 / elements.eccentricity).acos()
+    };
+
+    let mut last: Option<[f64; 2]> = None;
+    for i in 0..=CONIC_SEGMENTS {
+        let anomaly = -max_anomaly + 2.0 * max_anomaly * i as f64 / CONIC_SEGMENTS as f64;
+        let point = elements
+            .position_at(anomaly)
+            .filter(|_| elements.radius_at(anomaly).unwrap_or(f64::INFINITY) < MAX_CONIC_RADIUS)
+            .map(|p| [p[0] + focus[0], p[1] + focus[1]]);
+        if let (Some(from), Some(to)) = (last, point) {
+            renderer.line(GHOST, 0.5, [from[0], from[1], to[0], to[1]]);
+        }
+        last = point;
+    }
+}
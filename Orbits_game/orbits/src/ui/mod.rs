@@ -0,0 +1,5 @@
+pub mod hud;
+pub mod inspector;
+pub mod scrubber;
+pub mod text;
+pub mod toasts;
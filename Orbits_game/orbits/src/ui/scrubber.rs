@@ -0,0 +1,52 @@
+use crate::render::renderer::Renderer;
+use crate::simulation::timeline::Timeline;
+use crate::utils::colour::Colour;
+
+const TRACK: Colour = [0.3, 0.3, 0.3, 0.8];
+const FILLED: Colour = [0.4, 0.6, 1.0, 0.8];
+const HANDLE: Colour = [1.0; 4];
+const HEIGHT: f64 = 10.0;
+const MARGIN: f64 = 20.0;
+
+/// Slider along the bottom of the window to
+/// scrub through the timeline with the mouse
+pub struct Scrubber {
+    rect: [f64; 4],
+    pub dragging: bool,
+}
+
+impl Scrubber {
+    pub fn new(bounds: f64) -> Scrubber {
+        Scrubber {
+            rect: [
+                MARGIN,
+                bounds - MARGIN - HEIGHT,
+                bounds - 2.0 * MARGIN,
+                HEIGHT,
+            ],
+            dragging: false,
+        }
+    }
+
+    /// Whether a point lies on the slider, with some room around it
+    pub fn contains(&self, point: [f64; 2]) -> bool {
+        let [x, y, w, h] = self.rect;
+        point[0] >= x && point[0] <= x + w && point[1] >= y - h && point[1] <= y + 2.0 * h
+    }
+
+    /// Fraction of the slider to the left of the point
+    pub fn fraction_at(&self, point: [f64; 2]) -> f64 {
+        ((point[0] - self.rect[0]) / self.rect[2]).clamp(0.0, 1.0)
+    }
+
+    pub fn draw<R: Renderer>(&self, timeline: &Timeline, renderer: &mut R) {
+        if timeline.len() < 2 {
+            return;
+        }
+        let [x, y, w, h] = self.rect;
+        let fraction = timeline.position() as f64 / (timeline.len() - 1) as f64;
+        renderer.rectangle(TRACK, self.rect);
+        renderer.rectangle(FILLED, [x, y, w * fraction, h]);
+        renderer.rectangle(HANDLE, [x + w * fraction - 2.0, y - 3.0, 4.0, h + 6.0]);
+    }
+}
@@ -0,0 +1,36 @@
+use crate::render::renderer::Renderer;
+use crate::utils::colour::Colour;
+
+const FONT_SIZE: u32 = 14;
+const LINE_HEIGHT: f64 = 18.0;
+const PADDING: f64 = 8.0;
+const PANEL: Colour = [0.0, 0.0, 0.0, 0.6];
+
+/// Draw a block of text lines on a translucent panel,
+/// with its top-left corner at the given position.
+/// Returns the height of the panel, so panels can be stacked
+pub fn draw_panel<R: Renderer>(
+    lines: &[String],
+    pos: [f64; 2],
+    colour: Colour,
+    renderer: &mut R,
+) -> f64 {
+    if lines.is_empty() {
+        return 0.0;
+    }
+    // Monospace font, so every glyph is roughly 0.6 of the font size wide
+    let longest = lines
+        .iter()
+        .map(|line| line.chars().count())
+        .max()
+        .unwrap_or(0);
+    let width = longest as f64 * FONT_SIZE as f64 * 0.6 + 2.0 * PADDING;
+    let height = lines.len() as f64 * LINE_HEIGHT + 2.0 * PADDING;
+    renderer.rectangle(PANEL, [pos[0], pos[1], width, height]);
+
+    for (i, line) in lines.iter().enumerate() {
+        let baseline = pos[1] + PADDING + (i + 1) as f64 * LINE_HEIGHT - 4.0;
+        renderer.text(colour, FONT_SIZE, [pos[0] + PADDING, baseline], line);
+    }
+    height
+}
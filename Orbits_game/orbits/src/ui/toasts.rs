@@ -0,0 +1,58 @@
+use super::text;
+use crate::render::renderer::Renderer;
+use crate::simulation::events::SimEvent;
+use crate::simulation::units::UnitSystem;
+use crate::utils::colour::{self, Colour};
+use std::collections::VecDeque;
+use std::slice;
+use std::time::{Duration, Instant};
+
+const TEXT: Colour = [1.0, 1.0, 0.8, 1.0];
+// How long a toast stays up, it fades out over the last second
+const LIFETIME: Duration = Duration::from_secs(4);
+const FADE: f64 = 1.0;
+// Toasts shown at once, older ones are pushed out
+const MAX_TOASTS: usize = 5;
+
+/// Short-lived notifications of simulation events
+pub struct Toasts {
+    pub visible: bool,
+    entries: VecDeque<(String, Instant)>,
+}
+
+impl Toasts {
+    pub fn new() -> Toasts {
+        Toasts {
+            visible: true,
+            entries: VecDeque::new(),
+        }
+    }
+
+    pub fn push(&mut self, event: &SimEvent, units: Option<&UnitSystem>) {
+        self.show(event.describe(units));
+    }
+
+    /// Show a line of text that is not about a simulation event
+    pub fn show(&mut self, line: String) {
+        if self.entries.len() == MAX_TOASTS {
+            self.entries.pop_front();
+        }
+        self.entries.push_back((line, Instant::now()));
+    }
+
+    /// Draw the toasts stacked down from the position,
+    /// dropping the ones that have expired
+    pub fn draw<R: Renderer>(&mut self, pos: [f64; 2], renderer: &mut R) {
+        self.entries.retain(|(_, shown)| shown.elapsed() < LIFETIME);
+        if !self.visible {
+            return;
+        }
+        let mut y = pos[1];
+        for (line, shown) in self.entries.iter() {
+            // Time passes since the retain, so a toast may have just expired
+            let left = LIFETIME.saturating_sub(shown.elapsed()).as_secs_f64();
+            let colour = colour::fade(TEXT, (left / FADE).min(1.0));
+            y += text::draw_panel(slice::from_ref(line), [pos[0], y], colour, renderer) + 4.0;
+        }
+    }
+}
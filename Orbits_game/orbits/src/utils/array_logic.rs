@@ -47,3 +47,9 @@ pub fn dot_product(array_1: [f64; 2], array_2: [f64; 2]) -> f64 {
 pub fn add_arrays(array1: [f64; 2], array2: [f64; 2]) -> [f64; 2] {
     [array1[0] + array2[0], array1[1] + array2[1]]
 }
+
+/// Returns the z-component of the cross-product of
+/// two arrays, i.e. the 2D 'perp dot' product
+pub fn cross_product(array_1: [f64; 2], array_2: [f64; 2]) -> f64 {
+    array_1[0] * array_2[1] - array_1[1] * array_2[0]
+}
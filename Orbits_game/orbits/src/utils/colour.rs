@@ -1 +1,323 @@
+/// Red, green, blue and alpha, each from 0 to 1
 pub type Colour = [f32; 4];
+
+pub const WHITE: Colour = [1.0; 4];
+pub const BLACK: Colour = [0.0, 0.0, 0.0, 1.0];
+pub const RED: Colour = [1.0, 0.0, 0.0, 1.0];
+pub const TRANSPARENT: Colour = [0.0; 4];
+
+/// Linearly interpolate between two colours, t runs from 0 to 1
+pub fn lerp(from: Colour, to: Colour, t: f64) -> Colour {
+    let t = t.clamp(0.0, 1.0) as f32;
+    let mut colour = [0.0; 4];
+    for i in 0..4 {
+        colour[i] = from[i] + (to[i] - from[i]) * t;
+    }
+    colour
+}
+
+/// The same colour with a different alpha
+pub fn with_alpha(colour: Colour, alpha: f32) -> Colour {
+    [colour[0], colour[1], colour[2], alpha.clamp(0.0, 1.0)]
+}
+
+/// Scale the alpha of a colour, e.g. to fade out older trail points
+pub fn fade(colour: Colour, factor: f64) -> Colour {
+    with_alpha(colour, colour[3] * factor.clamp(0.0, 1.0) as f32)
+}
+
+/// Composite `over` on top of `under` (the "over" operator)
+pub fn blend(over: Colour, under: Colour) -> Colour {
+    let alpha = over[3].clamp(0.0, 1.0);
+    let out_alpha = alpha + under[3] * (1.0 - alpha);
+    if out_alpha <= 0.0 {
+        return TRANSPARENT;
+    }
+    let mut colour = [0.0; 4];
+    for i in 0..3 {
+        colour[i] = (over[i] * alpha + under[i] * under[3] * (1.0 - alpha)) / out_alpha;
+    }
+    colour[3] = out_alpha;
+    colour
+}
+
+/// Colour from hue in degrees, saturation and value from 0 to 1
+pub fn from_hsv(hue: f64, saturation: f64, value: f64, alpha: f32) -> Colour {
+    let hue = hue.rem_euclid(360.0) / 60.0;
+    let chroma = value * saturation;
+    let x = chroma * (1.0 - (hue % 2.0 - 1.0).abs());
+    let (r, g, b) = hue_sector(hue, chroma, x);
+    let m = value - chroma;
+    [(r + m) as f32, (g + m) as f32, (b + m) as f32, alpha]
+}
+
+/// Hue in degrees, saturation and value of a colour
+// Only the inverse of from_hsv for now, checked against it by the tests
+#[cfg_attr(not(test), allow(dead_code))]
+pub fn to_hsv(colour: Colour) -> (f64, f64, f64) {
+    let (max, min, hue) = hue_of(colour);
+    let saturation = if max > 0.0 { (max - min) / max } else { 0.0 };
+    (hue, saturation, max)
+}
+
+/// Colour from hue in degrees, saturation and lightness from 0 to 1
+// Not drawn with yet, kept correct by the tests
+#[cfg_attr(not(test), allow(dead_code))]
+pub fn from_hsl(hue: f64, saturation: f64, lightness: f64, alpha: f32) -> Colour {
+    let hue = hue.rem_euclid(360.0) / 60.0;
+    let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+    let x = chroma * (1.0 - (hue % 2.0 - 1.0).abs());
+    let (r, g, b) = hue_sector(hue, chroma, x);
+    let m = lightness - chroma / 2.0;
+    [(r + m) as f32, (g + m) as f32, (b + m) as f32, alpha]
+}
+
+/// Hue in degrees, saturation and lightness of a colour
+// Only the inverse of from_hsl for now, checked against it by the tests
+#[cfg_attr(not(test), allow(dead_code))]
+pub fn to_hsl(colour: Colour) -> (f64, f64, f64) {
+    let (max, min, hue) = hue_of(colour);
+    let lightness = (max + min) / 2.0;
+    let saturation = if max > min {
+        (max - min) / (1.0 - (2.0 * lightness - 1.0).abs())
+    } else {
+        0.0
+    };
+    (hue, saturation, lightness)
+}
+
+/// Red, green and blue before adding the minimum, for a hue from 0 to 6
+fn hue_sector(hue: f64, chroma: f64, x: f64) -> (f64, f64, f64) {
+    match hue as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    }
+}
+
+/// Largest and smallest channel and the hue in degrees
+#[cfg_attr(not(test), allow(dead_code))]
+fn hue_of(colour: Colour) -> (f64, f64, f64) {
+    let (r, g, b) = (colour[0] as f64, colour[1] as f64, colour[2] as f64);
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let delta = max - min;
+    let hue = if delta == 0.0 {
+        0.0
+    } else if max == r {
+        60.0 * ((g - b) / delta).rem_euclid(6.0)
+    } else if max == g {
+        60.0 * ((b - r) / delta + 2.0)
+    } else {
+        60.0 * ((r - g) / delta + 4.0)
+    };
+    (max, min, hue)
+}
+
+/// Parse a colour written as #RGB, #RRGGBB or #RRGGBBAA,
+/// the leading # is optional
+pub fn parse_hex(text: &str) -> Result<Colour, String> {
+    let digits = text.trim().trim_start_matches('#');
+    let invalid = || format!("Invalid colour '{}', expected #RRGGBB or #RRGGBBAA", text);
+    if !digits.is_ascii() {
+        return Err(invalid());
+    }
+    let channel = |hex: &str| u8::from_str_radix(hex, 16).map(|value| value as f32 / 255.0);
+    let channels: Result<Vec<f32>, _> = match digits.len() {
+        // Every digit is repeated, #f80 is #ff8800
+        3 => digits
+            .chars()
+            .map(|c| channel(&c.to_string().repeat(2)))
+            .collect(),
+        6 | 8 => (0..digits.len())
+            .step_by(2)
+            .map(|i| channel(&digits[i..i + 2]))
+            .collect(),
+        _ => return Err(invalid()),
+    };
+    let channels = channels.map_err(|_| invalid())?;
+    let alpha = channels.get(3).copied().unwrap_or(1.0);
+    Ok([channels[0], channels[1], channels[2], alpha])
+}
+
+/// Write a colour as #RRGGBB, or #RRGGBBAA when it is not opaque
+pub fn to_hex(colour: Colour) -> String {
+    let byte = |channel: f32| (channel.clamp(0.0, 1.0) * 255.0).round() as u8;
+    let rgb = format!(
+        "#{:02x}{:02x}{:02x}",
+        byte(colour[0]),
+        byte(colour[1]),
+        byte(colour[2])
+    );
+    match byte(colour[3]) {
+        255 => rgb,
+        alpha => format!("{}{:02x}", rgb, alpha),
+    }
+}
+
+/// A distinct colour for every id that is the same in every run,
+/// hues are spread out by the golden angle
+pub fn for_id(id: u64) -> Colour {
+    const GOLDEN_ANGLE: f64 = 137.507_764;
+    let hue = (id as f64 * GOLDEN_ANGLE) % 360.0;
+    // Alternate the value a little so neighbouring hues differ more
+    let value = if id.is_multiple_of(2) { 0.95 } else { 0.8 };
+    from_hsv(hue, 0.65, value, 1.0)
+}
+
+/// Named sets of colours for bodies of different groups
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Palette {
+    Classic,
+    Pastel,
+    Vivid,
+}
+
+impl Palette {
+    pub fn from_name(name: &str) -> Option<Palette> {
+        match name {
+            "classic" => Some(Palette::Classic),
+            "pastel" => Some(Palette::Pastel),
+            "vivid" => Some(Palette::Vivid),
+            _ => None,
+        }
+    }
+
+    fn colours(&self) -> &'static [Colour] {
+        match self {
+            Palette::Classic => &[WHITE, RED, [1.0, 0.8, 0.2, 1.0], [0.3, 0.6, 1.0, 1.0]],
+            Palette::Pastel => &[
+                [0.98, 0.71, 0.68, 1.0],
+                [0.70, 0.80, 0.89, 1.0],
+                [0.80, 0.92, 0.77, 1.0],
+                [0.87, 0.80, 0.89, 1.0],
+                [1.00, 0.85, 0.65, 1.0],
+            ],
+            Palette::Vivid => &[
+                [0.90, 0.10, 0.29, 1.0],
+                [0.24, 0.71, 0.29, 1.0],
+                [1.00, 0.88, 0.10, 1.0],
+                [0.26, 0.39, 0.85, 1.0],
+                [0.96, 0.51, 0.19, 1.0],
+                [0.57, 0.12, 0.71, 1.0],
+            ],
+        }
+    }
+
+    /// Colour of the group with the index, wrapping around
+    pub fn colour(&self, index: usize) -> Colour {
+        let colours = self.colours();
+        colours[index % colours.len()]
+    }
+}
+
+/// Gradients mapping a value from 0 to 1 onto a colour
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Gradient {
+    /// Perceptually uniform dark blue to yellow, readable when printed in grey
+    Viridis,
+    /// Perceptually uniform black to light yellow through magenta
+    Magma,
+    /// Cold (blue) to hot (red)
+    Heat,
+}
+
+impl Gradient {
+    fn stops(&self) -> &'static [Colour] {
+        match self {
+            // Samples of matplotlib's viridis and magma at every quarter
+            Gradient::Viridis => &[
+                [0.267, 0.005, 0.329, 1.0],
+                [0.231, 0.322, 0.545, 1.0],
+                [0.129, 0.569, 0.549, 1.0],
+                [0.369, 0.788, 0.384, 1.0],
+                [0.993, 0.906, 0.144, 1.0],
+            ],
+            Gradient::Magma => &[
+                [0.001, 0.000, 0.014, 1.0],
+                [0.316, 0.071, 0.485, 1.0],
+                [0.716, 0.215, 0.475, 1.0],
+                [0.987, 0.536, 0.382, 1.0],
+                [0.987, 0.991, 0.750, 1.0],
+            ],
+            Gradient::Heat => &[
+                [0.2, 0.3, 1.0, 1.0],
+                [0.0, 0.9, 0.9, 1.0],
+                [1.0, 0.9, 0.1, 1.0],
+                [1.0, 0.1, 0.1, 1.0],
+            ],
+        }
+    }
+
+    /// Colour at t, which is clamped to 0..1
+    pub fn sample(&self, t: f64) -> Colour {
+        let stops = self.stops();
+        let scaled = t.clamp(0.0, 1.0) * (stops.len() - 1) as f64;
+        let index = (scaled.floor() as usize).min(stops.len() - 2);
+        lerp(stops[index], stops[index + 1], scaled - index as f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: Colour, b: Colour) {
+        for i in 0..4 {
+            assert!((a[i] - b[i]).abs() < 1e-5, "{:?} is not {:?}", a, b);
+        }
+    }
+
+    #[test]
+    fn hsv_round_trip() {
+        for colour in [
+            RED,
+            WHITE,
+            BLACK,
+            [0.2, 0.6, 0.9, 1.0],
+            [0.7, 0.7, 0.1, 0.5],
+        ] {
+            let (hue, saturation, value) = to_hsv(colour);
+            assert_close(from_hsv(hue, saturation, value, colour[3]), colour);
+        }
+        assert_close(from_hsv(120.0, 1.0, 1.0, 1.0), [0.0, 1.0, 0.0, 1.0]);
+        assert_close(from_hsv(-120.0, 1.0, 0.5, 1.0), [0.0, 0.0, 0.5, 1.0]);
+    }
+
+    #[test]
+    fn hsl_round_trip() {
+        for colour in [
+            RED,
+            WHITE,
+            BLACK,
+            [0.2, 0.6, 0.9, 1.0],
+            [0.7, 0.7, 0.1, 0.5],
+        ] {
+            let (hue, saturation, lightness) = to_hsl(colour);
+            assert_close(from_hsl(hue, saturation, lightness, colour[3]), colour);
+        }
+        assert_close(from_hsl(240.0, 1.0, 0.5, 1.0), [0.0, 0.0, 1.0, 1.0]);
+        assert_eq!(to_hsl([0.5, 0.5, 0.5, 1.0]), (0.0, 0.0, 0.5));
+    }
+
+    #[test]
+    fn hex_round_trip() {
+        for text in ["#ff8800", "#1a2b3c", "#00000080"] {
+            assert_eq!(to_hex(parse_hex(text).unwrap()), text);
+        }
+        assert_eq!(parse_hex("f80").unwrap(), parse_hex("#FF8800").unwrap());
+        assert_eq!(parse_hex(" #ffffff ").unwrap(), WHITE);
+        for text in ["#12345", "#gg0000", "", "#ééé"] {
+            assert!(parse_hex(text).is_err(), "{} was parsed", text);
+        }
+    }
+
+    #[test]
+    fn ids_get_distinct_colours() {
+        assert_eq!(for_id(3), for_id(3));
+        assert_ne!(for_id(3), for_id(4));
+    }
+}
@@ -1,12 +1,18 @@
-use super::array_logic::{dot_product, scalar_mult, subtract_arrays, Normalise};
+use super::array_logic::{
+    add_arrays, dot_product, scalar_mult, subtract_arrays, Length, Normalise,
+};
 use crate::celestial_bodies::body_config::CelestialBody;
 
 /// Calculate the gravitational force between two bodies,
-/// takes two structs that implement the CelestialBody trait
+/// takes two (possibly different) structs that implement the CelestialBody trait
 /// this means that the body:
 /// - Has mass
 /// - Has pos
-pub fn grav_force<C: CelestialBody>(mass1: &C, mass2: &C, g: f64) -> ([f64; 2], [f64; 2]) {
+pub fn grav_force<A: CelestialBody, B: CelestialBody>(
+    mass1: &A,
+    mass2: &B,
+    g: f64,
+) -> ([f64; 2], [f64; 2]) {
     let dist = subtract_arrays(mass1.pos(), mass2.pos());
     let sqr_dist = dot_product(dist, dist); // dist.x^2 + dist.y^2
     let force_dir = dist.normalise();
@@ -15,3 +21,44 @@ pub fn grav_force<C: CelestialBody>(mass1: &C, mass2: &C, g: f64) -> ([f64; 2],
     let force_inv = scalar_mult(force, -1.0); // equally, pull on m2 by m1
     (force, force_inv)
 }
+
+/// Calculate the gravitational potential energy of a pair
+/// of bodies, -G * m1 * m2 / r
+pub fn grav_potential<A: CelestialBody, B: CelestialBody>(mass1: &A, mass2: &B, g: f64) -> f64 {
+    let dist = subtract_arrays(mass1.pos(), mass2.pos()).get_length();
+    -g * mass1.mass() * mass2.mass() / dist
+}
+
+/// Calculate the first post-Newtonian (1PN) correction to the
+/// gravitational force between two bodies, with `c` the speed
+/// of light in simulation units. The correction is the
+/// Schwarzschild-like relative acceleration
+///
+/// a = GM/(c^2 r^2) * [(4GM/r - v^2) n + 4 (n.v) v]
+///
+/// with M the total mass and r, v the relative position and velocity,
+/// applied to both bodies through their reduced mass. Like `grav_force`
+/// it returns the (force, force_inv) pair, to be added on top of it.
+pub fn pn_correction<A: CelestialBody, B: CelestialBody>(
+    mass1: &A,
+    mass2: &B,
+    g: f64,
+    c: f64,
+) -> ([f64; 2], [f64; 2]) {
+    let rel_pos = subtract_arrays(mass1.pos(), mass2.pos());
+    let rel_vel = subtract_arrays(mass1.vel(), mass2.vel());
+    let dist = rel_pos.get_length();
+    let n = rel_pos.normalise();
+    let total_mass = mass1.mass() + mass2.mass();
+    let reduced_mass = mass1.mass() * mass2.mass() / total_mass;
+    let gm = g * total_mass;
+
+    let radial = scalar_mult(n, 4.0 * gm / dist - dot_product(rel_vel, rel_vel));
+    let tangential = scalar_mult(rel_vel, 4.0 * dot_product(n, rel_vel));
+    let acc = scalar_mult(add_arrays(radial, tangential), gm / (c * c * dist * dist));
+
+    // The relative acceleration acts on m1, the opposite reaction on m2
+    let force_inv = scalar_mult(acc, reduced_mass);
+    let force = scalar_mult(force_inv, -1.0);
+    (force, force_inv)
+}